@@ -1,15 +1,60 @@
-use crate::sort::sort;
-use crate::util::read_file;
-use itertools::Itertools;
+use crate::secrets::Scanner;
+use hstr::{Blacklist, Error, Files, Index, List, Query, Search};
 use maplit::hashmap;
-use regex::{escape, Regex, RegexBuilder};
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum View {
     Sorted = 0,
     Favorites = 1,
     All = 2,
+    Hidden = 3,
+}
+
+/// A setting under which a query that matches nothing would match.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Hint {
+    ToggleCase,
+    ToggleRegex,
+    ShowBlacklisted,
+    SwitchView(View),
+}
+
+/* An edit made during this session, with enough information to undo it.
+ *
+ * For favorites and hidden commands, `position` is where the command
+ * used to be before it was removed, or None if it was added.
+ * For deletions, `lines` are the deleted lines of the history file,
+ * along with their positions, in ascending order.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Favorite {
+        command: String,
+        position: Option<usize>,
+    },
+    Hide {
+        command: String,
+        position: Option<usize>,
+    },
+    Blacklist(String),
+    Delete {
+        lines: Vec<(usize, String)>,
+    },
+}
+
+impl Edit {
+    /// Puts deleted commands back into the history, where they were.
+    pub fn restore_lines(&self, history: &mut Vec<String>) {
+        if let Edit::Delete { lines } = self {
+            for (position, command) in lines {
+                history.insert((*position).min(history.len()), command.clone());
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -20,11 +65,17 @@ pub struct Application {
     pub regex_mode: bool,
     pub case_sensitivity: bool,
     pub search_string: String,
-    pub shell: String,
+    pub files: Files,
+    /// Where the index of the history is cached.
+    pub cache: PathBuf,
+    pub blacklist: Blacklist,
+    pub show_blacklisted: bool,
+    pub edits: Vec<Edit>,
+    pub secrets: Scanner,
 }
 
 impl Application {
-    pub fn new(shell: &str) -> Self {
+    pub fn new(files: Files, cache: PathBuf) -> Self {
         Self {
             to_restore: None,
             commands: None,
@@ -32,48 +83,161 @@ impl Application {
             regex_mode: false,
             case_sensitivity: false,
             search_string: String::new(),
-            shell: shell.to_string(),
+            files,
+            cache,
+            blacklist: Blacklist::default(),
+            show_blacklisted: false,
+            edits: Vec::new(),
+            secrets: Scanner::default(),
         }
     }
 
-    pub fn load_commands(&mut self) {
-        let history = read_file(format!(".{}_history", self.shell)).unwrap();
+    pub fn load_commands(&mut self) -> Result<(), Error> {
+        let history = load_index(&self.files, &self.cache)?;
         let commands = hashmap! {
-            View::All => history.clone().into_iter().unique().collect(),
-            View::Sorted => sort(history),
-            View::Favorites => read_file(
-                format!(
-                    ".config/hstr-rs/.{}_favorites",
-                    self.shell
-                )
-            ).unwrap()
+            View::All => history.all(),
+            View::Sorted => history.sorted(),
+            View::Favorites => List::Favorites.load(&self.files)?,
+            View::Hidden => List::Hidden.load(&self.files)?
         };
-        self.to_restore = Some(commands.clone());
-        self.commands = Some(commands);
+        self.blacklist
+            .set_commands(List::Blacklist.load(&self.files)?);
+        self.to_restore = Some(commands);
+        self.restore();
+        Ok(())
+    }
+
+    pub fn reload_history(&mut self) -> Result<(), Error> {
+        let history = load_index(&self.files, &self.cache)?;
+        self.replace_commands(View::All, history.all());
+        self.replace_commands(View::Sorted, history.sorted());
+        Ok(())
+    }
+
+    pub fn reload_favorites(&mut self) -> Result<(), Error> {
+        let favorites = List::Favorites.load(&self.files)?;
+        self.replace_commands(View::Favorites, favorites);
+        Ok(())
+    }
+
+    fn replace_commands(&mut self, view: View, commands: Vec<String>) {
+        self.to_restore
+            .as_mut()
+            .unwrap()
+            .insert(view, commands.clone());
+        let commands = self.visible(view, commands);
+        self.commands.as_mut().unwrap().insert(view, commands);
+        if view == self.view {
+            self.search();
+        }
     }
 
     pub fn restore(&mut self) {
-        self.commands = self.to_restore.clone();
+        self.commands = self.to_restore.as_ref().map(|to_restore| {
+            to_restore
+                .iter()
+                .map(|(view, commands)| (*view, self.visible(*view, commands.clone())))
+                .collect()
+        });
+    }
+
+    fn visible(&self, view: View, mut commands: Vec<String>) -> Vec<String> {
+        if view == View::Sorted || view == View::All {
+            let hidden = self.hidden_set();
+            commands.retain(|x| self.is_visible(x, &hidden, self.show_blacklisted));
+        }
+        commands
+    }
+
+    fn is_visible(&self, command: &str, hidden: &HashSet<&str>, show_blacklisted: bool) -> bool {
+        !hidden.contains(command) && (show_blacklisted || !self.blacklist.contains(command))
+    }
+
+    fn hidden_set(&self) -> HashSet<&str> {
+        self.get_hidden().iter().map(|x| x.as_str()).collect()
+    }
+
+    /// Commands hidden from the sorted and all views, regardless of
+    /// the current search.
+    pub fn get_hidden(&self) -> &[String] {
+        self.get_stored(View::Hidden)
+    }
+
+    /// All favorites, regardless of the current search.
+    pub fn get_favorites(&self) -> &[String] {
+        self.get_stored(View::Favorites)
+    }
+
+    fn get_stored(&self, view: View) -> &[String] {
+        self.to_restore
+            .as_ref()
+            .or(self.commands.as_ref())
+            .and_then(|x| x.get(&view))
+            .map_or(&[], |x| x.as_slice())
     }
 
     pub fn get_commands(&self) -> &[String] {
         self.commands.as_ref().unwrap().get(&self.view).unwrap()
     }
 
-    fn create_search_regex(&self) -> Option<Regex> {
-        let search_string = if self.regex_mode {
-            self.search_string.clone()
-        } else {
-            escape(&self.search_string)
+    fn query(&self) -> Query {
+        Query::new(&self.search_string, self.regex_mode, self.case_sensitivity)
+    }
+
+    /// When the query matches nothing, the settings under which it would.
+    pub fn hints(&self) -> Vec<Hint> {
+        let mut hints = Vec::new();
+        if self.search_string.is_empty() || !self.get_commands().is_empty() {
+            return hints;
+        }
+        let query = self.query();
+        let regex = query.compile();
+        let toggled_case = Query {
+            case_sensitive: !query.case_sensitive,
+            ..query.clone()
+        }
+        .compile();
+        if self.has_matches(self.view, toggled_case, self.show_blacklisted) {
+            hints.push(Hint::ToggleCase);
+        }
+        let toggled_regex = Query {
+            regex: !query.regex,
+            ..query
+        }
+        .compile();
+        if self.has_matches(self.view, toggled_regex, self.show_blacklisted) {
+            hints.push(Hint::ToggleRegex);
+        }
+        if !self.show_blacklisted && self.has_matches(self.view, regex.clone(), true) {
+            hints.push(Hint::ShowBlacklisted);
+        }
+        let mut view = self.view;
+        for _ in 0..3 {
+            view = next_view(view);
+            if self.has_matches(view, regex.clone(), self.show_blacklisted) {
+                hints.push(Hint::SwitchView(view));
+                break;
+            }
+        }
+        hints
+    }
+
+    fn has_matches(&self, view: View, regex: Option<Regex>, show_blacklisted: bool) -> bool {
+        let regex = match regex {
+            Some(r) => r,
+            None => return false,
         };
-        RegexBuilder::new(&search_string)
-            .case_insensitive(!self.case_sensitivity)
-            .build()
-            .ok()
+        let hidden = self.hidden_set();
+        self.get_stored(view).iter().any(|x| {
+            regex.is_match(x)
+                && (view == View::Favorites
+                    || view == View::Hidden
+                    || self.is_visible(x, &hidden, show_blacklisted))
+        })
     }
 
     pub fn search(&mut self) {
-        let search_regex = match self.create_search_regex() {
+        let search_regex = match self.query().compile() {
             Some(r) => r,
             None => {
                 return;
@@ -87,29 +251,154 @@ impl Application {
             .retain(|x| search_regex.is_match(x));
     }
 
+    pub fn start_search(&self, pending: Option<Search>) -> Option<Search> {
+        let query = self.query();
+        match pending {
+            Some(search) => Some(search.refine(&query)),
+            None => Search::new(self.get_commands().to_vec(), &query),
+        }
+    }
+
+    pub fn set_commands(&mut self, commands: Vec<String>) {
+        self.commands.as_mut().unwrap().insert(self.view, commands);
+    }
+
     pub fn add_or_rm_fav(&mut self, command: String) {
-        let favorites = self
-            .commands
+        let position = self.get_favorites().iter().position(|x| *x == command);
+        for commands in self.commands.iter_mut().chain(self.to_restore.iter_mut()) {
+            let favorites = commands.get_mut(&View::Favorites).unwrap();
+            if position.is_none() && !favorites.contains(&command) {
+                favorites.push(command.clone());
+            } else if position.is_some() {
+                favorites.retain(|x| *x != command);
+            }
+        }
+        self.edits.push(Edit::Favorite { command, position });
+    }
+
+    pub fn hide_or_unhide(&mut self, command: String) {
+        let hidden = self
+            .to_restore
             .as_mut()
             .unwrap()
-            .get_mut(&View::Favorites)
+            .get_mut(&View::Hidden)
             .unwrap();
-        if !favorites.contains(&command) {
-            favorites.push(command);
+        let position = hidden.iter().position(|x| *x == command);
+        if position.is_none() {
+            hidden.push(command.clone());
         } else {
-            favorites.retain(|x| *x != command);
+            hidden.retain(|x| *x != command);
         }
+        self.edits.push(Edit::Hide { command, position });
+        self.restore();
+        self.search();
     }
 
-    pub fn delete_from_history(&mut self, command: String) {
-        for view in [View::Sorted, View::Favorites, View::All].iter() {
-            self.commands
-                .as_mut()
-                .unwrap()
-                .get_mut(view)
-                .unwrap()
-                .retain(|x| *x != command);
+    /// Removes the command from the history, and from the views built
+    /// from it, keeping the current search applied. Favorites and hidden
+    /// commands have their own files, and are left as they are.
+    pub fn delete_from_history(&mut self, command: String, history: &mut Vec<String>) {
+        self.delete_all_from_history(&[command], history);
+    }
+
+    pub fn delete_all_from_history(&mut self, commands: &[String], history: &mut Vec<String>) {
+        let commands = commands.iter().collect::<HashSet<_>>();
+        let lines = history
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| commands.contains(x))
+            .map(|(i, x)| (i, x.clone()))
+            .collect();
+        history.retain(|x| !commands.contains(x));
+        for stored in self.commands.iter_mut().chain(self.to_restore.iter_mut()) {
+            for view in [View::Sorted, View::All].iter() {
+                stored
+                    .get_mut(view)
+                    .unwrap()
+                    .retain(|x| !commands.contains(x));
+            }
+        }
+        self.edits.push(Edit::Delete { lines });
+    }
+
+    /// Commands in the history matched by the current search among those
+    /// shown, in the order they first appear, with the number of lines
+    /// each of them takes up. Nothing matches an empty search, nor in the
+    /// favorites and hidden views, which are not the history.
+    pub fn find_in_history(&self, history: &[String]) -> Vec<(String, usize)> {
+        if let View::Favorites | View::Hidden = self.view {
+            return Vec::new();
+        }
+        let search_regex = match self.query().compile() {
+            Some(r) if !self.search_string.is_empty() => r,
+            _ => {
+                return Vec::new();
+            }
+        };
+        let shown = self.get_commands().iter().collect::<HashSet<_>>();
+        let mut matches: Vec<(String, usize)> = Vec::new();
+        let mut positions: HashMap<&String, usize> = HashMap::new();
+        let candidates = history
+            .iter()
+            .filter(|x| shown.contains(x) && search_regex.is_match(x));
+        for line in candidates {
+            match positions.get(line) {
+                Some(&i) => matches[i].1 += 1,
+                None => {
+                    positions.insert(line, matches.len());
+                    matches.push((line.clone(), 1));
+                }
+            }
+        }
+        matches
+    }
+
+    pub fn add_to_blacklist(&mut self, command: String) {
+        if self.blacklist.commands().contains(&command) {
+            return;
+        }
+        self.blacklist.add(command.clone());
+        if !self.show_blacklisted {
+            for view in [View::Sorted, View::All].iter() {
+                self.commands
+                    .as_mut()
+                    .unwrap()
+                    .get_mut(view)
+                    .unwrap()
+                    .retain(|x| *x != command);
+            }
+        }
+        self.edits.push(Edit::Blacklist(command));
+    }
+
+    /// Reverts the last edit in memory, and returns it, so that the
+    /// file it touched can be written back. Commands deleted from the
+    /// history only reappear once the history is reloaded.
+    pub fn undo(&mut self) -> Option<Edit> {
+        let edit = self.edits.pop()?;
+        match &edit {
+            Edit::Favorite { command, position } => {
+                self.revert(View::Favorites, command, *position)
+            }
+            Edit::Hide { command, position } => self.revert(View::Hidden, command, *position),
+            Edit::Blacklist(command) => self.blacklist.remove(command),
+            Edit::Delete { .. } => {}
         }
+        self.restore();
+        self.search();
+        Some(edit)
+    }
+
+    fn revert(&mut self, view: View, command: &str, position: Option<usize>) {
+        let stored = self.to_restore.as_mut().unwrap().get_mut(&view).unwrap();
+        match position {
+            Some(i) => stored.insert(i.min(stored.len()), command.to_string()),
+            None => stored.retain(|x| x != command),
+        }
+    }
+
+    pub fn toggle_blacklisted(&mut self) {
+        self.show_blacklisted = !self.show_blacklisted;
     }
 
     pub fn toggle_case(&mut self) {
@@ -121,68 +410,65 @@ impl Application {
     }
 
     pub fn toggle_view(&mut self) {
-        self.view = match (self.view as u8 + 1) % 3 {
-            0 => View::Sorted,
-            1 => View::Favorites,
-            2 => View::All,
-            _ => unreachable!(),
-        }
+        self.view = next_view(self.view);
     }
 }
 
-#[cfg(test)]
-pub mod fixtures {
-    use super::*;
-    use rstest::fixture;
-
-    #[fixture]
-    pub fn fake_history() -> Vec<String> {
-        vec![
-            "cat spam",
-            "cat SPAM",
-            "git add .",
-            "git add . --dry-run",
-            "git push origin master",
-            "git rebase -i HEAD~2",
-            "git checkout -b tests",
-            "grep -r spam .",
-            "ping -c 10 www.google.com",
-            "ls -la",
-            "lsusb",
-            "lspci",
-            "sudo reboot",
-            "source .venv/bin/activate",
-            "deactivate",
-            "pytest",
-            "cargo test",
-            "xfce4-panel -r",
-            "nano .gitignore",
-            "sudo dkms add .",
-            "cd ~/Downloads",
-            "make -j4",
-            "gpg --card-status",
-        ]
-        .iter()
-        .map(|&x| x.into())
-        .collect()
-    }
-
-    #[fixture]
-    pub fn app_with_fake_history(fake_history: Vec<String>) -> Application {
-        let mut app = Application::new("bash");
-        let fake_commands = hashmap! {
-            View::All => fake_history.clone(),
-            View::Favorites => Vec::new(),
-            View::Sorted => fake_history,
-        };
-        app.commands = Some(fake_commands);
-        app
+/* The history file is created if it is missing, e.g. before
+ * the shell first saved it. */
+fn load_index(files: &Files, cache: &Path) -> Result<Index, Error> {
+    let history = files.history();
+    if !history.exists() {
+        File::create(&history).map_err(|e| Error::Write(history.clone(), e))?;
+    }
+    Index::load(&history, cache).map_err(|e| Error::Read(history, e))
+}
+
+/// Where the index of the history of `shell` is cached by default.
+pub fn cache_path(shell: &str) -> Result<PathBuf, Error> {
+    let cache_dir = match dirs::cache_dir() {
+        Some(dir) => dir,
+        None => dirs::home_dir().ok_or(Error::NoHomeDir)?.join(".cache"),
+    };
+    Ok(cache_dir.join("hstr-rs").join(format!("{}_history", shell)))
+}
+
+fn next_view(view: View) -> View {
+    match (view as u8 + 1) % 4 {
+        0 => View::Sorted,
+        1 => View::Favorites,
+        2 => View::All,
+        3 => View::Hidden,
+        _ => unreachable!(),
     }
 }
 
+/// An application with its files nowhere, for tests that never touch them.
+#[cfg(test)]
+pub fn detached_app() -> Application {
+    let nowhere = Path::new("/nonexistent");
+    Application::new(Files::under(nowhere, "bash"), nowhere.join("cache"))
+}
+
+/// The fake history, as if it had been loaded.
+#[cfg(test)]
+#[rstest::fixture(fake_history = crate::fixtures::fake_history())]
+pub fn app_with_fake_history(fake_history: Vec<String>) -> Application {
+    let mut app = detached_app();
+    let fake_commands = hashmap! {
+        View::All => fake_history.clone(),
+        View::Favorites => Vec::new(),
+        View::Sorted => fake_history,
+        View::Hidden => Vec::new(),
+    };
+    app.commands = Some(fake_commands);
+    app
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{fixtures::*, *};
+    use super::*;
+    use crate::fixtures::*;
     use rstest::rstest;
 
     #[rstest(
@@ -205,7 +491,6 @@ mod tests {
         app_with_fake_history.regex_mode = regex_mode;
         app_with_fake_history.case_sensitivity = case_sensitivity;
         app_with_fake_history.search_string = String::from(search_string);
-        app_with_fake_history.create_search_regex();
         app_with_fake_history.search();
         assert_eq!(app_with_fake_history.get_commands(), expected);
     }
@@ -215,7 +500,8 @@ mod tests {
         expected,
         case(View::Sorted, fake_history()),
         case(View::Favorites, Vec::new()),
-        case(View::All, fake_history())
+        case(View::All, fake_history()),
+        case(View::Hidden, Vec::new())
     )]
     fn get_commands(view: View, expected: Vec<String>, mut app_with_fake_history: Application) {
         app_with_fake_history.view = view;
@@ -223,30 +509,6 @@ mod tests {
         assert_eq!(commands, expected);
     }
 
-    #[rstest(
-        search_string,
-        regex_mode,
-        case_sensitivity,
-        expected,
-        case(String::from("print("), false, false, "print\\("),
-        case(String::from("print("), true, false, ""),
-        case(String::from("print("), false, true, "print\\("),
-        case(String::from("print("), true, true, "")
-    )]
-    fn create_search_regex(
-        search_string: String,
-        regex_mode: bool,
-        case_sensitivity: bool,
-        expected: &str,
-        mut app_with_fake_history: Application,
-    ) {
-        app_with_fake_history.search_string = search_string;
-        app_with_fake_history.regex_mode = regex_mode;
-        app_with_fake_history.case_sensitivity = case_sensitivity;
-        let regex = app_with_fake_history.create_search_regex();
-        assert_eq!(regex.unwrap_or(Regex::new("").unwrap()).as_str(), expected);
-    }
-
     #[rstest(
         command,
         case(String::from("cat spam")),
@@ -271,34 +533,246 @@ mod tests {
             .contains(&command));
     }
 
+    #[rstest()]
+    fn rm_fav_while_searching(mut app_with_fake_history: Application) {
+        app_with_fake_history.view = View::Favorites;
+        for command in &["cat spam", "lsusb", "lspci"] {
+            app_with_fake_history.add_or_rm_fav(command.to_string());
+        }
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.search_string = String::from("ls");
+        app_with_fake_history.search();
+        app_with_fake_history.add_or_rm_fav(String::from("lsusb"));
+        assert_eq!(app_with_fake_history.get_commands(), vec!["lspci"]);
+        assert_eq!(
+            app_with_fake_history.get_favorites(),
+            vec!["cat spam", "lspci"]
+        );
+    }
+
     #[rstest(
         command,
         case(String::from("cat spam")),
         case(String::from("grep -r spam .")),
         case(String::from("ping -c 10 www.google.com"))
     )]
-    fn delete_from_history(command: String, mut app_with_fake_history: Application) {
-        app_with_fake_history.delete_from_history(command.clone());
+    fn delete_from_history(
+        command: String,
+        mut fake_history: Vec<String>,
+        mut app_with_fake_history: Application,
+    ) {
+        app_with_fake_history.delete_from_history(command.clone(), &mut fake_history);
+        assert!(!fake_history.contains(&command));
         assert!(!app_with_fake_history.get_commands().contains(&command));
     }
 
+    #[rstest(
+        view,
+        expected,
+        case(View::Sorted, false),
+        case(View::All, false),
+        case(View::Hidden, true)
+    )]
+    fn hide_or_unhide(view: View, expected: bool, mut app_with_fake_history: Application) {
+        let command = String::from("git add . --dry-run");
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.hide_or_unhide(command.clone());
+        app_with_fake_history.view = view;
+        assert_eq!(
+            app_with_fake_history.get_commands().contains(&command),
+            expected
+        );
+        app_with_fake_history.hide_or_unhide(command.clone());
+        assert_eq!(
+            app_with_fake_history.get_commands().contains(&command),
+            !expected
+        );
+    }
+
+    #[rstest(view, case(View::Sorted), case(View::All))]
+    fn delete_while_searching(view: View, mut app_with_fake_history: Application) {
+        app_with_fake_history.add_or_rm_fav(String::from("lsusb"));
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.view = view;
+        app_with_fake_history.search_string = String::from("ls");
+        app_with_fake_history.search();
+        app_with_fake_history.delete_from_history(String::from("lsusb"), &mut Vec::new());
+        assert_eq!(
+            app_with_fake_history.get_commands(),
+            vec!["ls -la", "lspci"]
+        );
+        app_with_fake_history.restore();
+        assert!(!app_with_fake_history
+            .get_commands()
+            .contains(&String::from("lsusb")));
+        assert_eq!(app_with_fake_history.get_favorites(), vec!["lsusb"]);
+    }
+
+    #[rstest()]
+    fn undo(mut app_with_fake_history: Application) {
+        for command in &["lsusb", "lspci", "pytest"] {
+            app_with_fake_history.add_or_rm_fav(command.to_string());
+        }
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.edits.clear();
+        let before = app_with_fake_history.clone();
+        app_with_fake_history.add_or_rm_fav(String::from("lspci"));
+        app_with_fake_history.add_or_rm_fav(String::from("make -j4"));
+        app_with_fake_history.hide_or_unhide(String::from("cargo test"));
+        app_with_fake_history.add_to_blacklist(String::from("pytest"));
+        while app_with_fake_history.undo().is_some() {}
+        assert_eq!(app_with_fake_history.commands, before.commands);
+        assert_eq!(app_with_fake_history.to_restore, before.to_restore);
+        assert!(app_with_fake_history.blacklist.commands().is_empty());
+    }
+
+    #[rstest()]
+    fn undo_delete(fake_history: Vec<String>, mut app_with_fake_history: Application) {
+        let mut history = fake_history.clone();
+        history.extend(vec![String::from("lsusb"), String::from("pytest")]);
+        history.extend(fake_history.clone());
+        let before = history.clone();
+        app_with_fake_history.delete_from_history(String::from("lsusb"), &mut history);
+        app_with_fake_history.delete_from_history(String::from("pytest"), &mut history);
+        while let Some(edit) = app_with_fake_history.edits.pop() {
+            edit.restore_lines(&mut history);
+        }
+        assert_eq!(history, before);
+    }
+
+    #[rstest(
+        search_string,
+        regex_mode,
+        case_sensitivity,
+        expected,
+        case("", false, false, vec![]),
+        case("SPAM", false, false, vec![("cat spam", 2), ("cat SPAM", 1), ("grep -r spam .", 1)]),
+        case("SPAM", false, true, vec![("cat SPAM", 1)]),
+        case("^git add", true, false, vec![("git add .", 1), ("git add . --dry-run", 1)]),
+        case("(", true, false, vec![])
+    )]
+    fn find_in_history(
+        search_string: &str,
+        regex_mode: bool,
+        case_sensitivity: bool,
+        expected: Vec<(&str, usize)>,
+        mut fake_history: Vec<String>,
+        mut app_with_fake_history: Application,
+    ) {
+        fake_history.push(String::from("cat spam"));
+        app_with_fake_history.search_string = String::from(search_string);
+        app_with_fake_history.regex_mode = regex_mode;
+        app_with_fake_history.case_sensitivity = case_sensitivity;
+        let matches = app_with_fake_history.find_in_history(&fake_history);
+        assert_eq!(
+            matches,
+            expected
+                .iter()
+                .map(|&(c, n)| (c.to_string(), n))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest()]
+    fn undo_delete_all(fake_history: Vec<String>, mut app_with_fake_history: Application) {
+        let mut history = fake_history.clone();
+        history.extend(fake_history.clone());
+        let before = history.clone();
+        app_with_fake_history.search_string = String::from("git ");
+        let matches = app_with_fake_history
+            .find_in_history(&history)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect::<Vec<_>>();
+        app_with_fake_history.delete_all_from_history(&matches, &mut history);
+        assert_eq!(history.len(), before.len() - 10);
+        app_with_fake_history
+            .edits
+            .pop()
+            .unwrap()
+            .restore_lines(&mut history);
+        assert_eq!(history, before);
+    }
+
     #[rstest(
         before,
         after,
         case(View::Sorted, View::Favorites),
         case(View::Favorites, View::All),
-        case(View::All, View::Sorted)
+        case(View::All, View::Hidden),
+        case(View::Hidden, View::Sorted)
     )]
     fn toggle_view(before: View, after: View) {
-        let mut app = Application::new("bash");
+        let mut app = detached_app();
         app.view = before;
         app.toggle_view();
         assert_eq!(app.view, after);
     }
 
+    #[rstest(view, case(View::Sorted), case(View::Favorites), case(View::All))]
+    fn add_to_blacklist(view: View, mut app_with_fake_history: Application) {
+        let command = String::from("ls -la");
+        app_with_fake_history.add_or_rm_fav(command.clone());
+        app_with_fake_history.add_to_blacklist(command.clone());
+        app_with_fake_history.view = view;
+        assert!(app_with_fake_history.blacklist.contains(&command));
+        assert_eq!(
+            app_with_fake_history.get_commands().contains(&command),
+            view == View::Favorites
+        );
+    }
+
+    #[rstest(
+        view,
+        search_string,
+        case_sensitivity,
+        regex_mode,
+        expected,
+        case(View::Sorted, "CAT", true, false, vec![Hint::ToggleCase]),
+        case(View::Sorted, "git.*tests", false, false, vec![Hint::ToggleRegex]),
+        case(View::Sorted, "lsusb", false, false, vec![Hint::ShowBlacklisted]),
+        case(View::Favorites, "git", false, false, vec![Hint::SwitchView(View::All)]),
+        case(View::Sorted, "zzz", false, false, vec![]),
+        case(View::Sorted, "git", false, false, vec![])
+    )]
+    fn hints(
+        view: View,
+        search_string: &str,
+        case_sensitivity: bool,
+        regex_mode: bool,
+        expected: Vec<Hint>,
+        mut app_with_fake_history: Application,
+    ) {
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history
+            .blacklist
+            .set_commands(vec![String::from("lsusb")]);
+        app_with_fake_history.view = view;
+        app_with_fake_history.search_string = String::from(search_string);
+        app_with_fake_history.case_sensitivity = case_sensitivity;
+        app_with_fake_history.regex_mode = regex_mode;
+        app_with_fake_history.restore();
+        app_with_fake_history.search();
+        assert_eq!(app_with_fake_history.hints(), expected);
+    }
+
+    #[rstest(show_blacklisted, case(true), case(false))]
+    fn restore(show_blacklisted: bool, mut app_with_fake_history: Application) {
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.blacklist = Blacklist::new(Vec::new(), 6);
+        app_with_fake_history.show_blacklisted = show_blacklisted;
+        app_with_fake_history.restore();
+        assert_eq!(
+            app_with_fake_history
+                .get_commands()
+                .contains(&String::from("lsusb")),
+            show_blacklisted
+        );
+    }
+
     #[rstest(regex_mode, case(true), case(false))]
     fn toggle_regex_mode(regex_mode: bool) {
-        let mut app = Application::new("bash");
+        let mut app = detached_app();
         app.regex_mode = regex_mode;
         app.toggle_regex_mode();
         assert_eq!(app.regex_mode, !regex_mode);
@@ -306,9 +780,47 @@ mod tests {
 
     #[rstest(case_sensitivity, case(true), case(false))]
     fn toggle_case(case_sensitivity: bool) {
-        let mut app = Application::new("bash");
+        let mut app = detached_app();
         app.case_sensitivity = case_sensitivity;
         app.toggle_case();
         assert_eq!(app.case_sensitivity, !case_sensitivity);
     }
+
+    #[rstest(
+        search_string,
+        regex_mode,
+        case_sensitivity,
+        case("cat", false, false),
+        case("spam", false, false),
+        case("SPAM", false, true),
+        case("[0-9]+", true, false)
+    )]
+    fn same_as_sync_search(
+        search_string: &str,
+        regex_mode: bool,
+        case_sensitivity: bool,
+        mut app_with_fake_history: Application,
+    ) {
+        app_with_fake_history.regex_mode = regex_mode;
+        app_with_fake_history.case_sensitivity = case_sensitivity;
+        app_with_fake_history.search_string = String::from(search_string);
+        let search = app_with_fake_history.start_search(None).unwrap();
+        let matches = search.wait();
+        app_with_fake_history.search();
+        assert_eq!(app_with_fake_history.get_commands(), matches);
+    }
+
+    #[rstest()]
+    fn refine(mut app_with_fake_history: Application) {
+        app_with_fake_history.regex_mode = true;
+        let mut sync_app = app_with_fake_history.clone();
+        let mut search = None;
+        for search_string in &["g", "g.t", "g.t ", "g.t [ac]"] {
+            app_with_fake_history.search_string = String::from(*search_string);
+            search = app_with_fake_history.start_search(search);
+            sync_app.search_string = String::from(*search_string);
+            sync_app.search();
+        }
+        assert_eq!(sync_app.get_commands(), search.unwrap().wait());
+    }
 }
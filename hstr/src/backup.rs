@@ -0,0 +1,299 @@
+use hstr::Error;
+use std::collections::HashMap;
+use std::fs::{copy, create_dir_all, read_dir, remove_file, File, OpenOptions};
+use std::io::{stdin, stdout, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SHOWN_IN_SUMMARY: usize = 10;
+
+/* Timestamped copies of a history file, taken right before it gets
+ * rewritten. Backups are named `<file name>.<UTC timestamp>`, so that
+ * sorting them by name sorts them by age, and only the newest `count`
+ * of them are kept.
+ */
+pub struct Backups {
+    dir: PathBuf,
+    count: usize,
+}
+
+impl Backups {
+    pub fn new(dir: PathBuf, count: usize) -> Self {
+        Self { dir, count }
+    }
+
+    pub fn create(&self, file: &Path) -> Result<Option<PathBuf>, Error> {
+        if self.count == 0 || !file.exists() {
+            return Ok(None);
+        }
+        create_dir_all(&self.dir).map_err(|e| Error::Write(self.dir.clone(), e))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let name = format!(
+            "{}.{}",
+            file.file_name().unwrap().to_string_lossy(),
+            format_timestamp(now.as_secs(), now.subsec_millis())
+        );
+        /* Backups taken within the same millisecond get a counter after
+         * the timestamp, which still sorts them by age, rather than
+         * overwriting one another. */
+        let mut backup = self.dir.join(&name);
+        let mut n = 0;
+        let mut out = loop {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&backup)
+            {
+                Ok(out) => break out,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && n < 999 => {
+                    n += 1;
+                    backup = self.dir.join(format!("{}.{:03}", name, n));
+                }
+                Err(e) => return Err(Error::Write(backup, e)),
+            }
+        };
+        let mut input = File::open(file).map_err(|e| Error::Read(file.to_path_buf(), e))?;
+        std::io::copy(&mut input, &mut out).map_err(|e| Error::Write(backup.clone(), e))?;
+        let backups = self
+            .list(file)
+            .map_err(|e| Error::Read(self.dir.clone(), e))?;
+        for old in backups.into_iter().skip(self.count) {
+            remove_file(&old).map_err(|e| Error::Write(old, e))?;
+        }
+        Ok(Some(backup))
+    }
+
+    /// Backups of the given file, newest first.
+    pub fn list(&self, file: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}.", file.file_name().unwrap().to_string_lossy());
+        let mut backups = read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with(&prefix))
+            })
+            .collect::<Vec<_>>();
+        backups.sort();
+        backups.reverse();
+        Ok(backups)
+    }
+
+    /// Lists the backups of the history file on the terminal, and puts
+    /// back the one the user picks, after showing how it differs from
+    /// the current history. The current history is backed up first,
+    /// so a restore can itself be undone.
+    pub fn restore_interactively(&self, history: &Path) -> Result<(), Error> {
+        let backups = self.list(history)?;
+        if backups.is_empty() {
+            println!(
+                "No backups of {} in {}",
+                history.display(),
+                self.dir.display()
+            );
+            return Ok(());
+        }
+        for (index, backup) in backups.iter().enumerate() {
+            println!(
+                "{:>3}) {} ({} lines)",
+                index + 1,
+                backup.file_name().unwrap().to_string_lossy(),
+                read_lines(backup)?.len()
+            );
+        }
+        let answer = ask("Restore which backup? (number, empty to cancel) ")?;
+        let backup = match answer.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= backups.len() => &backups[n - 1],
+            _ => return Ok(()),
+        };
+        let summary = Summary::new(&read_lines(history)?, &read_lines(backup)?);
+        print!("{}", summary);
+        if ask(&format!("Restore {}? y/n ", backup.display()))? != "y" {
+            return Ok(());
+        }
+        self.create(history)?;
+        copy(backup, history).map_err(|e| Error::Write(history.to_path_buf(), e))?;
+        println!("Restored {}", history.display());
+        Ok(())
+    }
+}
+
+/// How restoring a backup would change the history, counted per line.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub restored: Vec<(String, usize)>,
+    pub lost: Vec<(String, usize)>,
+}
+
+impl Summary {
+    pub fn new(current: &[String], backup: &[String]) -> Self {
+        let current_freq = count(current);
+        let backup_freq = count(backup);
+        Self {
+            restored: difference(backup, &backup_freq, &current_freq),
+            lost: difference(current, &current_freq, &backup_freq),
+        }
+    }
+
+    fn total(lines: &[(String, usize)]) -> usize {
+        lines.iter().map(|(_, n)| n).sum()
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (sign, lines, label) in [
+            ('+', &self.restored, "restored"),
+            ('-', &self.lost, "removed"),
+        ]
+        .iter()
+        {
+            writeln!(f, "{} lines would be {}", Self::total(lines), label)?;
+            for (command, n) in lines.iter().take(SHOWN_IN_SUMMARY) {
+                writeln!(f, "  {} {} (x{})", sign, command, n)?;
+            }
+            if lines.len() > SHOWN_IN_SUMMARY {
+                writeln!(f, "  ... and {} more", lines.len() - SHOWN_IN_SUMMARY)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many times each line occurs.
+fn count(lines: &[String]) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for line in lines {
+        *counts.entry(line.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Lines occurring more often in `lines` than in `other`, in the order
+/// they first appear, with the number of extra occurrences.
+fn difference(
+    lines: &[String],
+    freq: &HashMap<&str, usize>,
+    other_freq: &HashMap<&str, usize>,
+) -> Vec<(String, usize)> {
+    let mut seen = Vec::new();
+    for line in lines {
+        let extra =
+            freq[line.as_str()].saturating_sub(*other_freq.get(line.as_str()).unwrap_or(&0));
+        if extra > 0 && !seen.iter().any(|(x, _)| x == line) {
+            seen.push((line.clone(), extra));
+        }
+    }
+    seen
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, std::io::Error> {
+    BufReader::new(File::open(path)?).lines().collect()
+}
+
+fn ask(question: &str) -> Result<String, std::io::Error> {
+    print!("{}", question);
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Formats a UNIX timestamp as YYYYmmdd-HHMMSS.mmm, in UTC.
+fn format_timestamp(secs: u64, millis: u32) -> String {
+    /* Civil from days, as described in
+     * http://howardhinnant.github.io/date_algorithms.html */
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let time = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}.{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs::write;
+    use tempfile::TempDir;
+
+    #[rstest(
+        secs,
+        millis,
+        expected,
+        case(0, 0, "19700101-000000.000"),
+        case(951_782_400, 5, "20000229-000000.005"),
+        case(1_700_000_000, 123, "20231114-221320.123")
+    )]
+    fn format_timestamp(secs: u64, millis: u32, expected: &str) {
+        assert_eq!(super::format_timestamp(secs, millis), expected);
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = TempDir::new().unwrap();
+        let history = dir.path().join(".bash_history");
+        let backups = Backups::new(dir.path().join("backups"), 3);
+        for i in 0..5 {
+            write(&history, format!("echo {}\n", i)).unwrap();
+            backups.create(&history).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let kept = backups.list(&history).unwrap();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(read_lines(&kept[0]).unwrap(), vec!["echo 4"]);
+        assert_eq!(read_lines(&kept[2]).unwrap(), vec!["echo 2"]);
+    }
+
+    #[test]
+    fn same_millisecond() {
+        let dir = TempDir::new().unwrap();
+        let history = dir.path().join(".bash_history");
+        let backups = Backups::new(dir.path().join("backups"), 5);
+        for i in 0..3 {
+            write(&history, format!("echo {}\n", i)).unwrap();
+            backups.create(&history).unwrap();
+        }
+        let kept = backups.list(&history).unwrap();
+        let contents = kept.iter().map(|x| read_lines(x).unwrap().join(""));
+        assert_eq!(
+            contents.collect::<Vec<_>>(),
+            vec!["echo 2", "echo 1", "echo 0"]
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let current = ["ls", "ls", "cd", "pwd"]
+            .iter()
+            .map(|&x| x.into())
+            .collect::<Vec<String>>();
+        let backup = ["ls", "ls", "ls", "cd", "top", "top"]
+            .iter()
+            .map(|&x| x.into())
+            .collect::<Vec<String>>();
+        let summary = Summary::new(&current, &backup);
+        assert_eq!(
+            summary.restored,
+            vec![(String::from("ls"), 1), (String::from("top"), 2)]
+        );
+        assert_eq!(summary.lost, vec![(String::from("pwd"), 1)]);
+    }
+}
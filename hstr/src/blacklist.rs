@@ -0,0 +1,74 @@
+use regex::Regex;
+
+/* Commands that are kept in the history file, but never suggested
+ * in the sorted and all views.
+ *
+ * A command is blacklisted if it was added to the blacklist file
+ * verbatim, if it matches one of the patterns from the config,
+ * or if it is shorter than the configured minimum length.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Blacklist {
+    pub(crate) commands: Vec<String>,
+    pub(crate) patterns: Vec<Regex>,
+    pub(crate) min_length: usize,
+}
+
+impl Blacklist {
+    pub fn new(patterns: Vec<Regex>, min_length: usize) -> Self {
+        Self {
+            commands: Vec::new(),
+            patterns,
+            min_length,
+        }
+    }
+
+    /// The commands added verbatim, as kept in the blacklist file.
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    pub fn set_commands(&mut self, commands: Vec<String>) {
+        self.commands = commands;
+    }
+
+    pub fn contains(&self, command: &str) -> bool {
+        command.chars().count() < self.min_length
+            || self.commands.iter().any(|x| x == command)
+            || self.patterns.iter().any(|r| r.is_match(command))
+    }
+
+    pub fn add(&mut self, command: String) {
+        if !self.commands.contains(&command) {
+            self.commands.push(command);
+        }
+    }
+
+    pub fn remove(&mut self, command: &str) {
+        self.commands.retain(|x| x != command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        command,
+        expected,
+        case("ls", true),
+        case("pwd", true),
+        case("exit", true),
+        case("cd ~/Downloads", true),
+        case("cd", true),
+        case("cdrecord dev=1,0,0", false),
+        case("ls -la", false)
+    )]
+    fn contains(command: &str, expected: bool) {
+        let mut blacklist = Blacklist::new(vec![Regex::new("^cd( |$)").unwrap()], 3);
+        blacklist.add(String::from("pwd"));
+        blacklist.add(String::from("exit"));
+        assert_eq!(blacklist.contains(command), expected);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::sort::{frequency_map, position_map, rank};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, metadata, rename, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+const MAGIC: &str = "hstr-rs cache v1";
+const TAIL_SIZE: u64 = 256;
+
+/* On-disk index of a history file.
+ *
+ * The cache file starts with a small header:
+ *
+ *   hstr-rs cache v1
+ *   <inode> <size> <mtime seconds> <mtime nanoseconds> <number of lines>
+ *   <last bytes of the history file, hex-encoded>
+ *
+ * followed by one line per unique command, in the order in which
+ * commands first appear in the history:
+ *
+ *   <frequency> <last position> <rank>\t<command>
+ *
+ * If the history file has the same inode, size and mtime as recorded
+ * in the header, the index is used as is. If the file only grew, and
+ * its bytes right before the old size still match the recorded tail,
+ * it is assumed that new commands were appended, and only those are
+ * parsed. Anything else means the file was rewritten, and the index
+ * is rebuilt from scratch.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct Index {
+    stamp: Stamp,
+    tail: Vec<u8>,
+    lines: usize,
+    commands: Vec<String>,
+    freq_map: HashMap<String, usize>,
+    pos_map: HashMap<String, usize>,
+    ranking: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Stamp {
+    inode: u64,
+    size: u64,
+    mtime: i64,
+    mtime_nsec: i64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Result<Self, std::io::Error> {
+        let meta = metadata(path)?;
+        Ok(Self {
+            inode: meta.ino(),
+            size: meta.size(),
+            mtime: meta.mtime(),
+            mtime_nsec: meta.mtime_nsec(),
+        })
+    }
+}
+
+impl Index {
+    /// Indexes the history file, starting from the index cached in
+    /// `cache` if it is still good, and writes the result back there.
+    pub fn load(history: &Path, cache: &Path) -> Result<Self, std::io::Error> {
+        let stamp = Stamp::of(history)?;
+        let mut index = Self::read(cache).unwrap_or_default();
+        if index.stamp == stamp {
+            return Ok(index);
+        }
+        if !index.is_prefix_of(history, &stamp)? {
+            index = Self::default();
+        }
+        let mut file = File::open(history)?;
+        file.seek(SeekFrom::Start(index.stamp.size))?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()?;
+        index.extend(lines);
+        index.stamp = stamp;
+        index.tail = read_tail(history, stamp.size)?;
+        /* A cache that cannot be written only costs us the next startup. */
+        let _ = index.write(cache);
+        Ok(index)
+    }
+
+    /// Unique commands, in the order they first appear in the history.
+    pub fn all(&self) -> Vec<String> {
+        self.commands.clone()
+    }
+
+    /// Unique commands, ranked the same way as `sort::sort` does it.
+    pub fn sorted(&self) -> Vec<String> {
+        self.ranking
+            .iter()
+            .map(|&i| self.commands[i].clone())
+            .collect()
+    }
+
+    fn is_prefix_of(&self, history: &Path, stamp: &Stamp) -> Result<bool, std::io::Error> {
+        if self.stamp.inode != stamp.inode
+            || self.stamp.size > stamp.size
+            || self.tail.last().is_some_and(|&b| b != b'\n')
+        {
+            return Ok(false);
+        }
+        Ok(read_tail(history, self.stamp.size)? == self.tail)
+    }
+
+    fn extend(&mut self, lines: Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+        let offset = self.lines;
+        for (command, freq) in frequency_map(&lines) {
+            *self.freq_map.entry(command).or_insert(0) += freq;
+        }
+        for (command, pos) in position_map(&lines) {
+            self.pos_map.insert(command, offset + pos);
+        }
+        self.lines += lines.len();
+        let mut known = self.commands.to_vec();
+        known.extend(lines.into_iter().unique());
+        self.commands = known.into_iter().unique().collect();
+        let positions = self
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.clone(), i))
+            .collect::<HashMap<_, _>>();
+        self.ranking = rank(self.commands.clone(), &self.freq_map, &self.pos_map)
+            .iter()
+            .map(|c| *positions.get(c).unwrap())
+            .collect();
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let mut lines = BufReader::new(file).lines();
+        if lines.next()?.ok()? != MAGIC {
+            return None;
+        }
+        let header = lines.next()?.ok()?;
+        let fields = header
+            .split(' ')
+            .map(|x| x.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if fields.len() != 5 {
+            return None;
+        }
+        let mut index = Self {
+            stamp: Stamp {
+                inode: fields[0] as u64,
+                size: fields[1] as u64,
+                mtime: fields[2],
+                mtime_nsec: fields[3],
+            },
+            lines: fields[4] as usize,
+            tail: from_hex(&lines.next()?.ok()?)?,
+            ..Self::default()
+        };
+        let mut ranked = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.ok()?;
+            let (numbers, command) = line.split_once('\t')?;
+            let numbers = numbers
+                .split(' ')
+                .map(|x| x.parse::<usize>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            if numbers.len() != 3 {
+                return None;
+            }
+            index.freq_map.insert(command.to_string(), numbers[0]);
+            index.pos_map.insert(command.to_string(), numbers[1]);
+            index.commands.push(command.to_string());
+            ranked.push((numbers[2], i));
+        }
+        ranked.sort_unstable();
+        index.ranking = ranked.into_iter().map(|(_, i)| i).collect();
+        Some(index)
+    }
+
+    fn write(&self, path: &Path) -> Result<(), std::io::Error> {
+        create_dir_all(path.parent().unwrap())?;
+        let tmp = path.with_extension("tmp");
+        let mut ranks = vec![0; self.commands.len()];
+        for (rank, &i) in self.ranking.iter().enumerate() {
+            ranks[i] = rank;
+        }
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(
+            writer,
+            "{} {} {} {} {}",
+            self.stamp.inode, self.stamp.size, self.stamp.mtime, self.stamp.mtime_nsec, self.lines
+        )?;
+        writeln!(writer, "{}", to_hex(&self.tail))?;
+        for (i, command) in self.commands.iter().enumerate() {
+            writeln!(
+                writer,
+                "{} {} {}\t{}",
+                self.freq_map.get(command).unwrap(),
+                self.pos_map.get(command).unwrap(),
+                ranks[i],
+                command
+            )?;
+        }
+        writer.flush()?;
+        rename(tmp, path)
+    }
+}
+
+fn read_tail(path: &Path, size: u64) -> Result<Vec<u8>, std::io::Error> {
+    let start = size.saturating_sub(TAIL_SIZE);
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.take(size - start).read_to_end(&mut tail)?;
+    Ok(tail)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(string: &str) -> Option<Vec<u8>> {
+    (0..string.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(string.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::sort::sort;
+    use rstest::rstest;
+    use std::fs::{write, OpenOptions};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn paths(dir: &TempDir) -> (PathBuf, PathBuf) {
+        (
+            dir.path().join(".bash_history"),
+            dir.path().join("cache").join("bash_history"),
+        )
+    }
+
+    #[rstest()]
+    fn same_as_sort(fake_history: Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        let (history, cache) = paths(&dir);
+        let mut lines = fake_history.clone();
+        lines.extend(fake_history.iter().step_by(3).cloned());
+        write(&history, lines.join("\n")).unwrap();
+        let index = Index::load(&history, &cache).unwrap();
+        assert_eq!(index.sorted(), sort(lines.clone()));
+        assert_eq!(index.all(), lines.into_iter().unique().collect::<Vec<_>>());
+    }
+
+    #[rstest()]
+    fn reads_back_cache(fake_history: Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        let (history, cache) = paths(&dir);
+        write(&history, fake_history.join("\n") + "\n").unwrap();
+        let index = Index::load(&history, &cache).unwrap();
+        assert_eq!(Index::read(&cache).unwrap(), index);
+    }
+
+    #[rstest()]
+    fn parses_appended_tail(fake_history: Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        let (history, cache) = paths(&dir);
+        let (old, new) = fake_history.split_at(10);
+        write(&history, old.join("\n") + "\n").unwrap();
+        Index::load(&history, &cache).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&history).unwrap();
+        file.write_all((new.join("\n") + "\ncat spam\n").as_bytes())
+            .unwrap();
+        let appended = Index::load(&history, &cache).unwrap();
+        std::fs::remove_file(&cache).unwrap();
+        let rebuilt = Index::load(&history, &cache).unwrap();
+        assert_eq!(appended.sorted(), rebuilt.sorted());
+        assert_eq!(appended.all(), rebuilt.all());
+        assert_eq!(appended.sorted()[0], "cat spam");
+    }
+
+    #[rstest()]
+    fn rebuilds_rewritten_file(fake_history: Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        let (history, cache) = paths(&dir);
+        write(&history, fake_history.join("\n") + "\n").unwrap();
+        Index::load(&history, &cache).unwrap();
+        write(&history, fake_history[1..].join("\n") + "\nlsblk\n").unwrap();
+        let index = Index::load(&history, &cache).unwrap();
+        assert!(!index.all().contains(&fake_history[0]));
+        assert!(index.all().contains(&String::from("lsblk")));
+    }
+}
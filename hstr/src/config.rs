@@ -0,0 +1,230 @@
+use regex::Regex;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The fewest lines to draw under the prompt: the prompt, the label
+/// and the status line, and at least one command.
+pub const MIN_HEIGHT: u16 = 4;
+
+/* User configuration, read from ~/.config/hstr-rs/config.
+ *
+ * Every line is a `key = value` pair. Keys holding a list can be
+ * repeated, once per item. Empty lines and lines starting with `#`
+ * are ignored, and so are unknown keys and values that don't parse.
+ *
+ *   # hide short commands and anything starting with cd
+ *   blacklist_min_length = 3
+ *   blacklist_pattern = ^cd( |$)
+ *   # keep the 10 latest copies of the history, taken before rewriting it
+ *   backup_count = 10
+ *   backup_dir = ~/backups/hstr-rs
+ *   # flag `vault login <token>` as a secret, in addition to the built-in patterns
+ *   secret_pattern = vault login (?P<secret>\S+)
+ *   # in presentation mode, also mask internal host names
+ *   mask_pattern = [a-z0-9-]+\.corp\.example\.com
+ *   # draw with crossterm rather than ncurses, if both were built in
+ *   backend = crossterm
+ *   # read ESC followed by x within 100ms as Alt+x, rather than 50ms
+ *   escape_timeout = 100
+ *   # leave the mouse to the terminal, e.g. to select text
+ *   mouse = false
+ *   # scroll the list a line at a time, rather than a page
+ *   scrolling = true
+ *   # draw 15 lines under the shell prompt, with the list going upwards
+ *   height = 15
+ *   bottom_up = true
+ */
+#[derive(Debug)]
+pub struct Config {
+    pub blacklist_patterns: Vec<Regex>,
+    pub blacklist_min_length: usize,
+    pub backup_count: usize,
+    pub backup_dir: PathBuf,
+    pub secret_patterns: Vec<Regex>,
+    pub mask_patterns: Vec<Regex>,
+    pub backend: Option<String>,
+    pub escape_timeout: Duration,
+    pub mouse: bool,
+    pub scrolling: bool,
+    /// Lines to draw under the prompt, rather than taking over the screen.
+    /// Only crossterm does, so this overrides `backend`.
+    pub height: Option<u16>,
+    pub bottom_up: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            blacklist_patterns: Vec::new(),
+            blacklist_min_length: 0,
+            backup_count: 5,
+            backup_dir: dirs::data_dir()
+                .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".local/share"))
+                .join("hstr-rs/backups"),
+            secret_patterns: Vec::new(),
+            mask_patterns: Vec::new(),
+            backend: None,
+            escape_timeout: Duration::from_millis(50),
+            mouse: true,
+            scrolling: false,
+            height: None,
+            bottom_up: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".config/hstr-rs/config");
+        match read_to_string(path) {
+            Ok(text) => Self::parse(&text.lines().map(String::from).collect::<Vec<_>>()),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn parse(lines: &[String]) -> Self {
+        let mut config = Self::default();
+        for line in lines.iter().map(|x| x.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => continue,
+            };
+            match key {
+                "blacklist_pattern" => {
+                    if let Ok(r) = Regex::new(value) {
+                        config.blacklist_patterns.push(r);
+                    }
+                }
+                "blacklist_min_length" => {
+                    if let Ok(n) = value.parse() {
+                        config.blacklist_min_length = n;
+                    }
+                }
+                "backup_count" => {
+                    if let Ok(n) = value.parse() {
+                        config.backup_count = n;
+                    }
+                }
+                "backup_dir" => config.backup_dir = expand_home(value),
+                "secret_pattern" => {
+                    if let Ok(r) = Regex::new(value) {
+                        config.secret_patterns.push(r);
+                    }
+                }
+                "mask_pattern" => {
+                    if let Ok(r) = Regex::new(value) {
+                        config.mask_patterns.push(r);
+                    }
+                }
+                "backend" => config.backend = Some(value.to_string()),
+                "escape_timeout" => {
+                    if let Ok(n) = value.parse() {
+                        config.escape_timeout = Duration::from_millis(n);
+                    }
+                }
+                "mouse" => {
+                    if let Ok(b) = value.parse() {
+                        config.mouse = b;
+                    }
+                }
+                "scrolling" => {
+                    if let Ok(b) = value.parse() {
+                        config.scrolling = b;
+                    }
+                }
+                "height" => {
+                    if let Ok(n) = value.parse::<u16>() {
+                        /* 0 is the whole screen, as when unset. */
+                        config.height = Some(n).filter(|&n| n > 0).map(|n| n.max(MIN_HEIGHT));
+                    }
+                }
+                "bottom_up" => {
+                    if let Ok(b) = value.parse() {
+                        config.bottom_up = b;
+                    }
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn parse() {
+        let lines = [
+            "# comment",
+            "",
+            "blacklist_min_length = 3",
+            "blacklist_pattern = ^cd( |$)",
+            "blacklist_pattern=^git (status|diff)$",
+            "blacklist_pattern = (",
+            "backup_count = 10",
+            "backup_dir = /tmp/hstr-rs",
+            "secret_pattern = vault login (?P<secret>\\S+)",
+            "mask_pattern = [a-z]+\\.corp",
+            "backend = crossterm",
+            "escape_timeout = 100",
+            "mouse = false",
+            "scrolling = true",
+            "height = 15",
+            "bottom_up = true",
+            "unknown = 1",
+            "garbage",
+        ]
+        .iter()
+        .map(|&x| x.into())
+        .collect::<Vec<String>>();
+        let config = Config::parse(&lines);
+        assert_eq!(config.blacklist_min_length, 3);
+        assert_eq!(config.backup_count, 10);
+        assert_eq!(config.backup_dir, PathBuf::from("/tmp/hstr-rs"));
+        assert_eq!(config.secret_patterns.len(), 1);
+        assert_eq!(config.mask_patterns.len(), 1);
+        assert_eq!(config.backend.as_deref(), Some("crossterm"));
+        assert_eq!(config.escape_timeout, Duration::from_millis(100));
+        assert!(!config.mouse);
+        assert!(config.scrolling);
+        assert_eq!(config.height, Some(15));
+        assert!(config.bottom_up);
+        assert_eq!(
+            config
+                .blacklist_patterns
+                .iter()
+                .map(|r| r.as_str())
+                .collect::<Vec<_>>(),
+            vec!["^cd( |$)", "^git (status|diff)$"]
+        );
+    }
+
+    #[rstest(
+        value,
+        expected,
+        case("0", None),
+        case("2", Some(MIN_HEIGHT)),
+        case("4", Some(4)),
+        case("tall", None)
+    )]
+    fn height(value: &str, expected: Option<u16>) {
+        let config = Config::parse(&[format!("height = {}", value)]);
+        assert_eq!(config.height, expected);
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    NoHomeDir,
+    Read(PathBuf, std::io::Error),
+    Write(PathBuf, std::io::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoHomeDir => write!(f, "could not find the home directory"),
+            Error::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            Error::Write(path, e) => write!(f, "could not write {}: {}", path.display(), e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NoHomeDir => None,
+            Error::Read(_, e) | Error::Write(_, e) | Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn display() {
+        let e = Error::Write(
+            PathBuf::from("/home/user/.bash_history"),
+            std::io::Error::new(ErrorKind::PermissionDenied, "permission denied"),
+        );
+        assert_eq!(
+            e.to_string(),
+            "could not write /home/user/.bash_history: permission denied"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use rstest::fixture;
+
+#[fixture]
+pub fn fake_history() -> Vec<String> {
+    vec![
+        "cat spam",
+        "cat SPAM",
+        "git add .",
+        "git add . --dry-run",
+        "git push origin master",
+        "git rebase -i HEAD~2",
+        "git checkout -b tests",
+        "grep -r spam .",
+        "ping -c 10 www.google.com",
+        "ls -la",
+        "lsusb",
+        "lspci",
+        "sudo reboot",
+        "source .venv/bin/activate",
+        "deactivate",
+        "pytest",
+        "cargo test",
+        "xfce4-panel -r",
+        "nano .gitignore",
+        "sudo dkms add .",
+        "cd ~/Downloads",
+        "make -j4",
+        "gpg --card-status",
+    ]
+    .iter()
+    .map(|&x| x.into())
+    .collect()
+}
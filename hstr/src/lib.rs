@@ -0,0 +1,38 @@
+//! The history handling behind hstr-rs, usable without its terminal
+//! front end.
+//!
+//! - [`Files`] tells where a shell's files are, [`read_history`] and
+//!   [`write_history`] read and write its history, and [`List`] the
+//!   favorites, hidden and blacklist lists kept next to it.
+//! - [`sort`] ranks commands by frequency and recency, and [`Index`]
+//!   does the same incrementally, with the ranking kept in a cache file.
+//! - [`Query`] turns what the user types into a regex, and [`Search`]
+//!   filters commands with it on a worker thread.
+//! - [`Blacklist`] tells the commands that are never suggested.
+//!
+//! ```
+//! use hstr::sort;
+//!
+//! let history = vec!["ls", "cd", "ls", "make", "cd", "ls"];
+//! assert_eq!(sort(history), ["ls", "cd", "make"]);
+//! ```
+
+mod blacklist;
+mod cache;
+mod error;
+mod query;
+mod search;
+mod sort;
+mod store;
+mod util;
+
+pub use blacklist::Blacklist;
+pub use cache::Index;
+pub use error::Error;
+pub use query::Query;
+pub use search::Search;
+pub use sort::sort;
+pub use store::{read_history, write_history, Files, List};
+
+#[cfg(test)]
+mod fixtures;
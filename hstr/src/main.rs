@@ -1,134 +1,258 @@
-use crate::app::Application;
+use crate::app::{cache_path, Application};
+use crate::backup::Backups;
+use crate::config::Config;
+use crate::record::{fingerprint, Input, Recorder, Recording};
+use crate::secrets::Scanner;
+use crate::session::{write_history, Action, Event, Session};
+use crate::shell::echo;
+use crate::terminal::headless::Headless;
+use crate::terminal::{Backend, Key, Read, Terminal};
 use crate::ui::UserInterface;
-use crate::util::write_file;
-use ncurses as nc;
+use crate::watch::Watcher;
+use hstr::{read_history, Blacklist, Error, Files, List};
 use setenv::get_shell;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tempfile::TempDir;
 
 mod app;
-mod sort;
+mod backup;
+mod config;
+#[cfg(test)]
+mod fixtures;
+mod record;
+mod secrets;
+mod session;
+mod shell;
+mod terminal;
 mod ui;
-mod util;
+mod watch;
 
-const CTRL_E: u32 = 5;
-const CTRL_F: u32 = 6;
-const TAB: u32 = 9;
-const ENTER: u32 = 10;
-const CTRL_T: u32 = 20;
-const ESC: u32 = 27;
-const CTRL_SLASH: u32 = 31;
-const Y: i32 = 121;
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("hstr-rs: {}", e);
+        std::process::exit(1);
+    }
+}
 
-fn main() -> Result<(), std::io::Error> {
-    nc::initscr();
-    nc::noecho();
-    nc::keypad(nc::stdscr(), true);
+fn run() -> Result<(), Error> {
     let shell = get_shell().get_name();
-    let mut app = Application::new(shell);
-    app.load_commands();
-    let mut user_interface = UserInterface::new();
-    user_interface.init_color_pairs();
-    user_interface.populate_screen(&app);
+    let config = Config::load();
+    let backups = Backups::new(config.backup_dir.clone(), config.backup_count);
+    let scanner = Scanner::new(config.secret_patterns.clone());
+    let files = Files::new(shell)?;
+    match std::env::args().nth(1).as_deref() {
+        Some("restore-backup") => return backups.restore_interactively(&files.history()),
+        Some("scrub") => {
+            let history = read_history(&files)?;
+            if let Some(scrubbed) = scanner.scrub_interactively(&history)? {
+                write_history(&backups, &files, &scrubbed)?;
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+    if let Some(path) = option("--replay") {
+        return replay(Path::new(&path), &files, &config);
+    }
+    let recorder = match option("--record") {
+        Some(path) => {
+            let history = std::fs::read(files.history()).unwrap_or_default();
+            let lists = fingerprint(&read_lists(&files));
+            Some(Recorder::create(
+                Path::new(&path),
+                fingerprint(&history),
+                lists,
+            )?)
+        }
+        None => None,
+    };
+    let app = load_app(files.clone(), cache_path(shell)?, &config)?;
+    let backend = Backend::from_config(&config);
+    set_panic_hook(backend);
+    let mut user_interface = new_user_interface(backend.open(&config)?, &config);
+    user_interface.error = Backend::conflict(&config).map(String::from);
+    let mut session = Session::new(app, user_interface, backups, Watcher::new(&files).ok());
+    interact(&mut session, recorder);
+    session.ui.term.end();
+    Ok(())
+}
+
+/// The value following the option on the command line, if any.
+fn option(name: &str) -> Option<String> {
+    std::env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn load_app(files: Files, cache: PathBuf, config: &Config) -> Result<Application, Error> {
+    let mut app = Application::new(files, cache);
+    app.secrets = Scanner::new(config.secret_patterns.clone());
+    app.blacklist = Blacklist::new(
+        config.blacklist_patterns.clone(),
+        config.blacklist_min_length,
+    );
+    app.load_commands()?;
+    Ok(app)
+}
+
+fn new_user_interface(term: Box<dyn Terminal>, config: &Config) -> UserInterface {
+    let mut user_interface = UserInterface::new(term);
+    user_interface.presenting = std::env::args().any(|x| x == "--present");
+    user_interface.scrolling = config.scrolling;
+    user_interface.bottom_up = config.bottom_up;
+    user_interface.masks = Scanner::new(
+        config
+            .secret_patterns
+            .iter()
+            .chain(config.mask_patterns.iter())
+            .cloned()
+            .collect(),
+    );
+    user_interface
+}
+
+/* Without this, a panic while the terminal is in raw mode leaves
+ * it that way, with the message scattered across the screen. */
+fn set_panic_hook(backend: Backend) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        backend.restore();
+        default_hook(info);
+    }));
+}
+
+fn interact(session: &mut Session, mut recorder: Option<Recorder>) {
+    let size = Input::Size(session.ui.term.lines(), session.ui.term.cols());
+    let mut failed = recorder.as_mut().and_then(|r| r.record(size).err());
     loop {
-        let user_input = nc::get_wch();
-        match user_input.unwrap() {
-            nc::WchResult::Char(ch) => match ch {
-                CTRL_E => {
-                    app.toggle_regex_mode();
-                    user_interface.selected = 0;
-                    user_interface.populate_screen(&app);
-                }
-                CTRL_F => {
-                    let commands = app.get_commands();
-                    let command = user_interface.get_selected(&commands);
-                    app.add_or_rm_fav(command);
-                    write_file(
-                        format!(".config/hstr-rs/.{}_favorites", shell),
-                        app.commands
-                            .as_ref()
-                            .unwrap()
-                            .get(&app::View::Favorites)
-                            .unwrap(),
-                    )?;
-                }
-                TAB => {
-                    let commands = app.get_commands();
-                    let command = user_interface.get_selected(&commands);
-                    util::echo(command);
-                    break;
-                }
-                ENTER => {
-                    let commands = app.get_commands();
-                    let command = user_interface.get_selected(&commands);
-                    util::echo(command);
-                    util::echo("\n".to_string());
-                    break;
-                }
-                CTRL_T => {
-                    app.toggle_case();
-                    user_interface.populate_screen(&app);
-                }
-                ESC => break,
-                CTRL_SLASH => {
-                    app.toggle_view();
-                    user_interface.selected = 0;
-                    user_interface.populate_screen(&app);
-                }
-                _ => {
-                    app.search_string
-                        .push(std::char::from_u32(ch as u32).unwrap());
-                    user_interface.selected = 0;
-                    user_interface.page = 1;
-                    nc::clear();
-                    app.search();
-                    user_interface.populate_screen(&app);
-                }
-            },
-            nc::WchResult::KeyCode(code) => match code {
-                nc::KEY_UP => {
-                    let commands = app.get_commands();
-                    user_interface.move_selected(commands, -1);
-                    user_interface.populate_screen(&app);
-                }
-                nc::KEY_DOWN => {
-                    let commands = app.get_commands();
-                    user_interface.move_selected(commands, 1);
-                    user_interface.populate_screen(&app);
-                }
-                nc::KEY_BACKSPACE => {
-                    app.search_string.pop();
-                    app.restore();
-                    nc::clear();
-                    app.search();
-                    user_interface.populate_screen(&app);
-                }
-                nc::KEY_DC => {
-                    let commands = app.get_commands();
-                    let command = user_interface.get_selected(&commands);
-                    user_interface.prompt_for_deletion(&command);
-                    if nc::getch() == Y {
-                        app.delete_from_history(command);
-                        write_file(format!(".{}_history", shell), app.get_commands())?;
-                    }
-                    app.load_commands();
-                    user_interface.populate_screen(&app);
-                }
-                nc::KEY_NPAGE => {
-                    let commands = app.get_commands();
-                    user_interface.turn_page(commands, 1);
-                    user_interface.populate_screen(&app);
+        /* A recording with holes in it would be misleading,
+         * so it stops at the first error. */
+        if let Some(e) = failed.take() {
+            recorder = None;
+            session.ui.report::<()>(Err(e));
+            session.ui.populate_screen(&session.app);
+        }
+        let event = match session.ui.term.read_key(session.timeout()) {
+            Read::Key(key) => Event::Key(key),
+            Read::Ignored => continue,
+            Read::Timeout => Event::Tick,
+        };
+        if let (Some(r), Event::Key(key)) = (recorder.as_mut(), event) {
+            let input = match key {
+                Key::Resize => Input::Size(session.ui.term.lines(), session.ui.term.cols()),
+                key => Input::Key(key),
+            };
+            failed = r.record(input).err();
+        }
+        let action = session.handle(event);
+        match action {
+            Some(Action::Insert { command, run }) => {
+                echo(command);
+                if run {
+                    echo("\n".to_string());
                 }
-                nc::KEY_PPAGE => {
-                    let commands = app.get_commands();
-                    user_interface.turn_page(commands, -1);
-                    user_interface.populate_screen(&app);
+                break;
+            }
+            Some(Action::Quit) => break,
+            None => {}
+        }
+    }
+}
+
+const LISTS: [List; 3] = [List::Favorites, List::Hidden, List::Blacklist];
+
+/// The lists one after the other, each ended with a NUL, so that moving
+/// a command from one to another changes the fingerprint.
+fn read_lists(files: &Files) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for list in &LISTS {
+        bytes.extend(std::fs::read(list.path(files)).unwrap_or_default());
+        bytes.push(0);
+    }
+    bytes
+}
+
+/* Replays a recording against a copy of the given history, or of the
+ * current one, and of the current lists, in a private temporary
+ * directory standing in for the home directory, so that whatever the
+ * recording deletes or marks as favorite leaves the real files alone. */
+fn replay(path: &Path, files: &Files, config: &Config) -> Result<(), Error> {
+    let recording = Recording::load(path)?;
+    let history = match option("--history") {
+        Some(history) => PathBuf::from(history),
+        None => files.history(),
+    };
+    let contents = std::fs::read(&history).map_err(|e| Error::Read(history.clone(), e))?;
+    if fingerprint(&contents) != recording.history {
+        eprintln!("hstr-rs: the history differs from the recorded one, so may the replay");
+    }
+    let home = TempDir::new()?;
+    let copy = Files::under(home.path(), files.shell());
+    std::fs::write(copy.history(), contents).map_err(|e| Error::Write(copy.history(), e))?;
+    if fingerprint(&read_lists(files)) != recording.lists {
+        eprintln!("hstr-rs: the favorites, hidden or blacklist differ from the recorded ones, so may the replay");
+    }
+    for list in &LISTS {
+        let (from, to) = (list.path(files), list.path(&copy));
+        if from.exists() {
+            std::fs::create_dir_all(to.parent().unwrap())?;
+            std::fs::copy(&from, &to).map_err(|e| Error::Write(to.clone(), e))?;
+        }
+    }
+    play(&recording, copy, config, home.path())
+}
+
+/// Plays the recording on screen with `--visual`, in real time, or else
+/// on a virtual screen, which is printed at the end.
+fn play(recording: &Recording, files: Files, config: &Config, home: &Path) -> Result<(), Error> {
+    let app = load_app(files, home.join("cache"), config)?;
+    let backups = Backups::new(home.join("backups"), config.backup_count);
+    let visual = std::env::args().any(|x| x == "--visual");
+    let (term, screen): (Box<dyn Terminal>, _) = if visual {
+        let backend = Backend::from_config(config);
+        set_panic_hook(backend);
+        (backend.open(config)?, None)
+    } else {
+        let headless = Headless::new(recording.size.0, recording.size.1);
+        let screen = headless.screen();
+        (Box::new(headless), Some(screen))
+    };
+    let mut session = Session::new(app, new_user_interface(term, config), backups, None);
+    let start = Instant::now();
+    let mut action = None;
+    for (at, input) in &recording.inputs {
+        if visual {
+            std::thread::sleep(at.saturating_sub(start.elapsed()));
+        }
+        let key = match *input {
+            Input::Key(key) => key,
+            Input::Size(lines, cols) => {
+                if let Some(screen) = &screen {
+                    screen.borrow_mut().resize(lines, cols);
                 }
-                _ => {}
-            },
+                Key::Resize
+            }
+        };
+        action = session.handle(Event::Key(key));
+        if action.is_some() {
+            break;
         }
     }
-    nc::clear();
-    nc::refresh();
-    nc::doupdate();
-    nc::endwin();
+    match screen {
+        Some(screen) => print!("{}", screen.borrow()),
+        None => {
+            /* Leave the last screen up until a key is pressed. */
+            session.ui.term.read_key(None);
+            session.ui.term.end();
+        }
+    }
+    match action {
+        Some(Action::Insert { command, run: true }) => println!("run: {}", command),
+        Some(Action::Insert {
+            command,
+            run: false,
+        }) => println!("insert: {}", command),
+        Some(Action::Quit) => println!("quit"),
+        None => println!("the recording ended before the session did"),
+    }
     Ok(())
 }
@@ -0,0 +1,65 @@
+use regex::{escape, Regex, RegexBuilder};
+
+/* What the user searches for.
+ *
+ * The text is taken literally unless `regex` is set, and letters match
+ * regardless of case unless `case_sensitive` is set.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    pub text: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+impl Query {
+    pub fn new(text: &str, regex: bool, case_sensitive: bool) -> Self {
+        Self {
+            text: text.to_string(),
+            regex,
+            case_sensitive,
+        }
+    }
+
+    /// The regex matching what the query asks for,
+    /// or None if the text is not a valid regex.
+    pub fn compile(&self) -> Option<Regex> {
+        let pattern = if self.regex {
+            self.text.clone()
+        } else {
+            escape(&self.text)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        text,
+        regex,
+        case_sensitive,
+        expected,
+        case("print(", false, false, Some("print\\(")),
+        case("print(", true, false, None),
+        case("print(", false, true, Some("print\\(")),
+        case("print(", true, true, None),
+        case("ls -la", true, false, Some("ls -la"))
+    )]
+    fn compile(text: &str, regex: bool, case_sensitive: bool, expected: Option<&str>) {
+        let query = Query::new(text, regex, case_sensitive);
+        assert_eq!(query.compile().as_ref().map(Regex::as_str), expected);
+    }
+
+    #[rstest(case_sensitive, expected, case(false, true), case(true, false))]
+    fn case_sensitivity(case_sensitive: bool, expected: bool) {
+        let regex = Query::new("SPAM", false, case_sensitive).compile().unwrap();
+        assert_eq!(regex.is_match("cat spam"), expected);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::terminal::Key;
+use hstr::Error;
+use std::fs::{read_to_string, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/* Recordings are text files, so that they can be attached to a bug
+ * report and read or edited by hand:
+ *
+ *   # hstr-rs recording
+ *   history 5d1c7a3e0f2b9c84
+ *   lists 9e3779b97f4a7c15
+ *   0 size 24x80
+ *   812 git
+ *   1033 <Down>
+ *   2410 size 30x100
+ *   3127 <Enter>
+ *
+ * The history line is a fingerprint of the history file the session
+ * started with, and the lists line one of the favorites, hidden and
+ * blacklist files together. Every other line is an input, prefixed
+ * with the number of milliseconds since the start. The first one is
+ * always the size of the terminal, and later ones record it being
+ * resized.
+ */
+const HEADER: &str = "# hstr-rs recording";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Input {
+    Key(Key),
+    /// The terminal, in lines and columns.
+    Size(i32, i32),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Recording {
+    pub history: u64,
+    pub lists: u64,
+    pub size: (i32, i32),
+    pub inputs: Vec<(Duration, Input)>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = read_to_string(path).map_err(|e| Error::Read(path.to_path_buf(), e))?;
+        Self::parse(&text).map_err(|e| {
+            Error::Read(
+                path.to_path_buf(),
+                std::io::Error::new(ErrorKind::InvalidData, e),
+            )
+        })
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate().skip(1);
+        if text.lines().next() != Some(HEADER) {
+            return Err(String::from("not a recording"));
+        }
+        let history = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("history "))
+            .and_then(|x| u64::from_str_radix(x, 16).ok())
+            .ok_or("line 2: no history fingerprint")?;
+        let lists = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("lists "))
+            .and_then(|x| u64::from_str_radix(x, 16).ok())
+            .ok_or("line 3: no lists fingerprint")?;
+        let mut inputs = Vec::new();
+        for (index, line) in lines {
+            let input = parse_input(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            inputs.push(input);
+        }
+        let size = match inputs.first() {
+            Some((_, Input::Size(lines, cols))) => (*lines, *cols),
+            _ => return Err(String::from("line 4: no terminal size")),
+        };
+        inputs.remove(0);
+        Ok(Self {
+            history,
+            lists,
+            size,
+            inputs,
+        })
+    }
+}
+
+fn parse_input(line: &str) -> Result<(Duration, Input), String> {
+    let (millis, input) = line.split_once(' ').ok_or("no timestamp")?;
+    let millis = millis
+        .parse()
+        .map_err(|_| format!("bad timestamp: {}", millis))?;
+    let input = match input.strip_prefix("size ") {
+        Some(size) => size
+            .split_once('x')
+            .and_then(|(l, c)| Some(Input::Size(l.parse().ok()?, c.parse().ok()?)))
+            .ok_or_else(|| format!("bad size: {}", size))?,
+        None => Input::Key(input.parse()?),
+    };
+    Ok((Duration::from_millis(millis), input))
+}
+
+/// Writes every input to a recording as it comes in.
+pub struct Recorder {
+    path: PathBuf,
+    out: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, history: u64, lists: u64) -> Result<Self, Error> {
+        let write = |e| Error::Write(path.to_path_buf(), e);
+        let mut out = BufWriter::new(File::create(path).map_err(write)?);
+        writeln!(
+            out,
+            "{}\nhistory {:016x}\nlists {:016x}",
+            HEADER, history, lists
+        )
+        .map_err(write)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            out,
+            start: Instant::now(),
+        })
+    }
+
+    /* Flushed every time, so that a recording of a session that
+     * crashed is complete. */
+    pub fn record(&mut self, input: Input) -> Result<(), Error> {
+        let millis = self.start.elapsed().as_millis();
+        match input {
+            Input::Key(key) => writeln!(self.out, "{} {}", millis, key),
+            Input::Size(lines, cols) => writeln!(self.out, "{} size {}x{}", millis, lines, cols),
+        }
+        .and_then(|_| self.out.flush())
+        .map_err(|e| Error::Write(self.path.clone(), e))
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` gives the same result
+/// whatever the build.
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[test]
+    fn record_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session");
+        let mut recorder = Recorder::create(&path, 0x5d1c7a3e0f2b9c84, 0x9e3779b97f4a7c15).unwrap();
+        recorder.record(Input::Size(24, 80)).unwrap();
+        recorder.record(Input::Key(Key::Char(' '))).unwrap();
+        recorder.record(Input::Key(Key::Down)).unwrap();
+        recorder.record(Input::Size(30, 100)).unwrap();
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.history, 0x5d1c7a3e0f2b9c84);
+        assert_eq!(recording.lists, 0x9e3779b97f4a7c15);
+        assert_eq!(recording.size, (24, 80));
+        assert_eq!(
+            recording
+                .inputs
+                .iter()
+                .map(|(_, input)| *input)
+                .collect::<Vec<_>>(),
+            vec![
+                Input::Key(Key::Char(' ')),
+                Input::Key(Key::Down),
+                Input::Size(30, 100)
+            ]
+        );
+    }
+
+    #[rstest(
+        text,
+        expected,
+        case("", "not a recording"),
+        case(
+            "# hstr-rs recording\n0 size 24x80\n",
+            "line 2: no history fingerprint"
+        ),
+        case(
+            "# hstr-rs recording\nhistory 1f\n0 size 24x80\n",
+            "line 3: no lists fingerprint"
+        ),
+        case(
+            "# hstr-rs recording\nhistory 1f\nlists 2e\n0 <Up>\n",
+            "line 4: no terminal size"
+        ),
+        case(
+            "# hstr-rs recording\nhistory 1f\nlists 2e\n0 size 24x80\nsoon <Up>\n",
+            "line 5: bad timestamp: soon"
+        ),
+        case(
+            "# hstr-rs recording\nhistory 1f\nlists 2e\n0 size 24x80\n5 size 24\n",
+            "line 5: bad size: 24"
+        ),
+        case(
+            "# hstr-rs recording\nhistory 1f\nlists 2e\n0 size 24x80\n5 <F0>\n",
+            "line 5: unknown key: <F0>"
+        )
+    )]
+    fn parse_errors(text: &str, expected: &str) {
+        assert_eq!(Recording::parse(text), Err(expected.to_string()));
+    }
+
+    #[test]
+    fn fingerprint() {
+        assert_eq!(super::fingerprint(b""), 0xcbf29ce484222325);
+        assert_eq!(super::fingerprint(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
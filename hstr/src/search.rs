@@ -0,0 +1,114 @@
+use crate::query::Query;
+use regex::Regex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+const CHUNK_SIZE: usize = 512;
+
+/* A search running on a worker thread.
+ *
+ * The worker filters its input in chunks and sends the matches of every
+ * chunk back as soon as they are known, so the screen can be updated
+ * while the search is still in progress.
+ *
+ * When the user types another character before the search is done,
+ * the stale search is cancelled and refined into a new one. The new
+ * search starts over from the same input, but keeps all the regexes
+ * of the stale one, so that the final result is exactly the same as if
+ * every keystroke had been filtered synchronously, one after another.
+ */
+pub struct Search {
+    input: Arc<Vec<String>>,
+    regexes: Arc<Vec<Regex>>,
+    matches: Vec<String>,
+    receiver: Receiver<Vec<String>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Search {
+    /// Starts filtering `input` with the query,
+    /// or returns None if the query is not a valid regex.
+    pub fn new(input: Vec<String>, query: &Query) -> Option<Self> {
+        let regex = query.compile()?;
+        Some(Self::spawn(Arc::new(input), Arc::new(vec![regex])))
+    }
+
+    /// Filters the matches further with the query. An invalid regex
+    /// leaves the search as it is.
+    pub fn refine(self, query: &Query) -> Self {
+        let regex = match query.compile() {
+            Some(r) => r,
+            None => return self,
+        };
+        self.cancel();
+        let mut regexes = self.regexes.to_vec();
+        regexes.push(regex);
+        Self::spawn(Arc::clone(&self.input), Arc::new(regexes))
+    }
+
+    fn spawn(input: Arc<Vec<String>>, regexes: Arc<Vec<Regex>>) -> Self {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker_input = Arc::clone(&input);
+        let worker_regexes = Arc::clone(&regexes);
+        let worker_cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            for chunk in worker_input.chunks(CHUNK_SIZE) {
+                if worker_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                let matches = chunk
+                    .iter()
+                    .filter(|x| worker_regexes.iter().all(|r| r.is_match(x)))
+                    .cloned()
+                    .collect();
+                if sender.send(matches).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            input,
+            regexes,
+            matches: Vec::new(),
+            receiver,
+            cancelled,
+        }
+    }
+
+    /// Collects whatever the worker has found so far without blocking.
+    /// Returns true once the search is complete.
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(matches) => self.matches.extend(matches),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => return true,
+            }
+        }
+    }
+
+    /// Blocks until the search is complete and returns all the matches.
+    pub fn wait(mut self) -> Vec<String> {
+        while let Ok(matches) = self.receiver.recv() {
+            self.matches.extend(matches);
+        }
+        std::mem::take(&mut self.matches)
+    }
+
+    pub fn matches(&self) -> &[String] {
+        &self.matches
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,727 @@
+use crate::app::{Application, Edit, View};
+use crate::backup::Backups;
+use crate::terminal::Key;
+use crate::ui::UserInterface;
+use crate::watch::{Change, Watcher};
+use hstr::{read_history, Error, Files, List, Search};
+use std::time::Duration;
+
+const CTRL_B: char = '\u{2}';
+const CTRL_C: char = '\u{3}';
+pub const CTRL_E: char = '\u{5}';
+const CTRL_F: char = '\u{6}';
+pub const CTRL_G: char = '\u{7}';
+const TAB: char = '\t';
+const ENTER: char = '\n';
+const CTRL_K: char = '\u{b}';
+pub const CTRL_P: char = '\u{10}';
+pub const CTRL_T: char = '\u{14}';
+const CTRL_X: char = '\u{18}';
+const CTRL_Z: char = '\u{1a}';
+const ESC: char = '\u{1b}';
+pub const CTRL_SLASH: char = '\u{1f}';
+const Y: Key = Key::Char('y');
+const SEARCH_POLL: Duration = Duration::from_millis(20);
+const SYNC_SEARCH_LIMIT: usize = 2048;
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    Key(Key),
+    /// No key was pressed before the timeout.
+    Tick,
+}
+
+/// How a session ends.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// Puts the command on the shell's command line, and runs it if `run`.
+    Insert {
+        command: String,
+        run: bool,
+    },
+    Quit,
+}
+
+/// A deletion waiting for the user to confirm it with `y`.
+enum Pending {
+    Unfavorite(String),
+    Delete(String),
+    DeleteAll(Vec<String>),
+}
+
+/* The state of an interactive session, which changes one event at
+ * a time. Favorites, hidden commands, the blacklist and the history
+ * are written as soon as they change, everything else is left to
+ * whoever reads the events and carries out the final action. */
+pub struct Session {
+    pub app: Application,
+    pub ui: UserInterface,
+    backups: Backups,
+    search: Option<Search>,
+    watcher: Option<Watcher>,
+    pending: Option<Pending>,
+    /// What was pasted so far, while a paste is coming in.
+    paste: Option<String>,
+}
+
+impl Session {
+    pub fn new(
+        app: Application,
+        mut ui: UserInterface,
+        backups: Backups,
+        watcher: Option<Watcher>,
+    ) -> Self {
+        ui.populate_screen(&app);
+        Self {
+            app,
+            ui,
+            backups,
+            search: None,
+            watcher,
+            pending: None,
+            paste: None,
+        }
+    }
+
+    /// How long to wait for a key before sending a tick, if at all.
+    pub fn timeout(&self) -> Option<Duration> {
+        match (&self.pending, &self.search, &self.watcher) {
+            (Some(_), _, _) => None,
+            (None, Some(_), _) => Some(SEARCH_POLL),
+            (None, None, Some(_)) => Some(WATCH_POLL),
+            (None, None, None) => None,
+        }
+    }
+
+    /// Reacts to the event, and returns an action if that ends the session.
+    pub fn handle(&mut self, event: Event) -> Option<Action> {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Tick => {
+                self.tick();
+                return None;
+            }
+        };
+        if let Some(paste) = self.paste.as_mut() {
+            match key {
+                Key::Char(ch) => paste.push(ch),
+                Key::PasteEnd => self.end_paste(),
+                _ => {}
+            }
+            return None;
+        }
+        if key == Key::PasteStart {
+            self.paste = Some(String::new());
+            return None;
+        }
+        if self.pending.is_some() && key.is_mouse() {
+            return None;
+        }
+        if let Some(pending) = self.pending.take() {
+            self.confirm(pending, key == Y);
+            return None;
+        }
+        self.ui.error = None;
+        if !is_query_edit(&key) {
+            self.finish_search();
+            self.ui.searching = false;
+        }
+        /* A bottom-up list goes on upwards. */
+        let up = if self.ui.bottom_up { 1 } else { -1 };
+        match key {
+            Key::Char(ch) => match ch {
+                CTRL_B => {
+                    let commands = self.app.get_commands().to_vec();
+                    if let Some(command) = self.ui.get_selected(&commands) {
+                        self.app.add_to_blacklist(command);
+                        let result =
+                            List::Blacklist.save(&self.app.files, self.app.blacklist.commands());
+                        self.ui.report(result);
+                    }
+                    self.ui.keep_selected(&commands, self.app.get_commands());
+                    self.ui.term.clear();
+                }
+                CTRL_E => {
+                    self.app.toggle_regex_mode();
+                    self.search_again();
+                }
+                CTRL_F => {
+                    if let Some(command) = self.ui.get_selected(self.app.get_commands()) {
+                        self.app.add_or_rm_fav(command);
+                        let result =
+                            List::Favorites.save(&self.app.files, self.app.get_favorites());
+                        self.ui.report(result);
+                    }
+                }
+                CTRL_G => {
+                    self.app.toggle_blacklisted();
+                    self.search_again();
+                }
+                TAB | ENTER => {
+                    if let Some(command) = self.ui.get_selected(self.app.get_commands()) {
+                        let run = ch == ENTER;
+                        return Some(Action::Insert { command, run });
+                    }
+                }
+                CTRL_K => {
+                    let result = self.hide_or_unhide();
+                    self.ui.report(result);
+                }
+                CTRL_P => {
+                    self.ui.presenting = !self.ui.presenting;
+                    self.ui.term.clear();
+                }
+                CTRL_T => {
+                    self.app.toggle_case();
+                    self.search_again();
+                }
+                CTRL_X => {
+                    let result = self.ask_to_delete_all();
+                    self.ui.report(result);
+                }
+                CTRL_Z => {
+                    let commands = self.app.get_commands().to_vec();
+                    if let Some(edit) = self.app.undo() {
+                        let result = self.write_undone(&edit);
+                        self.ui.report(result);
+                    }
+                    self.ui.keep_selected(&commands, self.app.get_commands());
+                    self.ui.term.clear();
+                }
+                CTRL_C | ESC => return Some(Action::Quit),
+                CTRL_SLASH => {
+                    self.app.toggle_view();
+                    self.search_again();
+                }
+                _ => self.extend_query(&ch.to_string()),
+            },
+            Key::Up => self.ui.move_selected(self.app.get_commands(), up),
+            Key::Down => self.ui.move_selected(self.app.get_commands(), -up),
+            Key::Backspace => {
+                self.search = None;
+                self.app.search_string.pop();
+                self.app.restore();
+                self.start_search(None);
+                self.ui.term.clear();
+            }
+            Key::Delete if self.app.view == View::Hidden => {
+                let result = self.hide_or_unhide();
+                self.ui.report(result);
+            }
+            Key::Delete => {
+                let result = self.ask_to_delete();
+                self.ui.report(result);
+            }
+            Key::PageDown => self.ui.turn_page(self.app.get_commands(), -up),
+            Key::PageUp => self.ui.turn_page(self.app.get_commands(), up),
+            Key::Home => self.ui.jump(self.app.get_commands(), 0),
+            Key::End => self.ui.jump(self.app.get_commands(), 100),
+            /* F1 to F9 jump to 10% to 90% of the list. */
+            Key::F(n @ 1..=9) => self.ui.jump(self.app.get_commands(), n as i32 * 10),
+            Key::Resize => self.ui.term.clear(),
+            Key::Alt(digit @ '1'..='9') => {
+                return self.insert_row(digit as i32 - '1' as i32);
+            }
+            /* Read, so that they neither quit nor end up in the query,
+             * but not bound to anything yet. */
+            Key::Alt(_) | Key::F(_) => {}
+            Key::Click { line, col } => match self.ui.screen_row(line) {
+                2 => {
+                    if let Some(key) = self.ui.status_key_at(&self.app, col) {
+                        return self.handle(Event::Key(key));
+                    }
+                }
+                row => {
+                    self.ui.select_row(self.app.get_commands(), row - 3);
+                }
+            },
+            Key::DoubleClick { line, .. } => return self.insert_row(self.ui.screen_row(line) - 3),
+            Key::WheelUp => self.ui.move_selected(self.app.get_commands(), up),
+            Key::WheelDown => self.ui.move_selected(self.app.get_commands(), -up),
+            /* Only the end of a paste that started during a prompt. */
+            Key::PasteStart | Key::PasteEnd => {}
+        }
+        /* A prompt stays on screen until the answer comes in. */
+        if self.pending.is_none() {
+            self.ui.populate_screen(&self.app);
+        }
+        None
+    }
+
+    /// Inserts the command on the given row of the page, if there is one.
+    fn insert_row(&mut self, row: i32) -> Option<Action> {
+        let commands = self.app.get_commands();
+        if !self.ui.select_row(commands, row) {
+            return None;
+        }
+        let command = self.ui.get_selected(commands)?;
+        Some(Action::Insert {
+            command,
+            run: false,
+        })
+    }
+
+    fn extend_query(&mut self, text: &str) {
+        self.app.search_string.push_str(text);
+        self.ui.select_first();
+        let pending = self.search.take();
+        self.start_search(pending);
+        self.ui.term.clear();
+    }
+
+    /* A paste goes into the query as a single edit, so that it is searched
+     * for once, and so that a newline in it doesn't run anything. During
+     * a prompt, it is dropped rather than taken for an answer. */
+    fn end_paste(&mut self) {
+        let text = pasted_query(&self.paste.take().unwrap_or_default());
+        if self.pending.is_some() || text.is_empty() {
+            return;
+        }
+        self.ui.error = None;
+        self.extend_query(&text);
+        self.ui.populate_screen(&self.app);
+    }
+
+    fn tick(&mut self) {
+        if self.pending.is_some() {
+            return;
+        }
+        /* Most ticks find nothing new, and redrawing for them would
+         * only make the terminal flicker. */
+        let searching = self.search.is_some();
+        self.poll_search();
+        let mut reloaded = false;
+        if self.search.is_none() {
+            if let Some(changes) = self.watcher.as_mut().map(|w| w.changes()) {
+                reloaded = self.reload(changes);
+            }
+        }
+        self.ui.searching = self.search.is_some();
+        if searching || reloaded {
+            self.ui.populate_screen(&self.app);
+        }
+    }
+
+    fn hide_or_unhide(&mut self) -> Result<(), Error> {
+        let commands = self.app.get_commands().to_vec();
+        let command = match self.ui.get_selected(&commands) {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        self.app.hide_or_unhide(command);
+        self.ui.keep_selected(&commands, self.app.get_commands());
+        self.ui.term.clear();
+        List::Hidden.save(&self.app.files, self.app.get_hidden())
+    }
+
+    fn ask_to_delete(&mut self) -> Result<(), Error> {
+        let command = match self.ui.get_selected(self.app.get_commands()) {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        if self.app.view == View::Favorites {
+            self.ui.prompt_for_deletion(&command, self.app.view, 0);
+            self.pending = Some(Pending::Unfavorite(command));
+        } else {
+            let history = read_history(&self.app.files)?;
+            let lines = history.iter().filter(|x| **x == command).count();
+            self.ui.prompt_for_deletion(&command, self.app.view, lines);
+            self.pending = Some(Pending::Delete(command));
+        }
+        Ok(())
+    }
+
+    fn ask_to_delete_all(&mut self) -> Result<(), Error> {
+        let history = read_history(&self.app.files)?;
+        let matches = self.app.find_in_history(&history);
+        if !matches.is_empty() {
+            self.ui.prompt_for_bulk_deletion(&matches);
+            let matches = matches.into_iter().map(|(c, _)| c).collect();
+            self.pending = Some(Pending::DeleteAll(matches));
+        }
+        Ok(())
+    }
+
+    fn confirm(&mut self, pending: Pending, confirmed: bool) {
+        let commands = self.app.get_commands().to_vec();
+        if confirmed {
+            let result = self.delete(pending);
+            self.ui.report(result);
+        }
+        self.ui.keep_selected(&commands, self.app.get_commands());
+        self.ui.term.clear();
+        self.ui.populate_screen(&self.app);
+    }
+
+    fn delete(&mut self, pending: Pending) -> Result<(), Error> {
+        let files = self.app.files.clone();
+        match pending {
+            Pending::Unfavorite(command) => {
+                self.app.add_or_rm_fav(command);
+                List::Favorites.save(&files, self.app.get_favorites())
+            }
+            Pending::Delete(command) => {
+                let mut history = read_history(&files)?;
+                self.app.delete_from_history(command, &mut history);
+                write_history(&self.backups, &files, &history)
+            }
+            Pending::DeleteAll(commands) => {
+                let mut history = read_history(&files)?;
+                self.app.delete_all_from_history(&commands, &mut history);
+                write_history(&self.backups, &files, &history)
+            }
+        }
+    }
+
+    fn write_undone(&mut self, edit: &Edit) -> Result<(), Error> {
+        let files = self.app.files.clone();
+        match edit {
+            Edit::Favorite { .. } => List::Favorites.save(&files, self.app.get_favorites()),
+            Edit::Hide { .. } => List::Hidden.save(&files, self.app.get_hidden()),
+            Edit::Blacklist(_) => List::Blacklist.save(&files, self.app.blacklist.commands()),
+            Edit::Delete { .. } => {
+                let mut history = read_history(&files)?;
+                edit.restore_lines(&mut history);
+                write_history(&self.backups, &files, &history)?;
+                self.app.reload_history()
+            }
+        }
+    }
+
+    /// Reloads the files that changed, and tells whether there were any.
+    fn reload(&mut self, changes: Vec<Change>) -> bool {
+        if changes.is_empty() {
+            return false;
+        }
+        let old = self.app.get_commands().to_vec();
+        for change in changes {
+            let result = match change {
+                Change::History => self.app.reload_history(),
+                Change::Favorites => self.app.reload_favorites(),
+            };
+            self.ui.report(result);
+        }
+        self.ui.keep_selected(&old, self.app.get_commands());
+        self.ui.term.clear();
+        true
+    }
+
+    /// Runs the query from scratch after a setting that affects its
+    /// results changed.
+    fn search_again(&mut self) {
+        self.app.restore();
+        self.app.search();
+        self.ui.select_first();
+        self.ui.term.clear();
+    }
+
+    fn start_search(&mut self, pending: Option<Search>) {
+        // Small histories are filtered faster than a worker thread can be spawned.
+        self.search = if pending.is_none() && self.app.get_commands().len() <= SYNC_SEARCH_LIMIT {
+            self.app.search();
+            None
+        } else {
+            self.app.start_search(pending)
+        };
+        self.ui.searching = self.search.is_some();
+    }
+
+    fn poll_search(&mut self) {
+        if let Some(s) = self.search.as_mut() {
+            let done = s.poll();
+            self.app.set_commands(s.matches().to_vec());
+            if done {
+                self.search = None;
+                self.ui.term.clear();
+            }
+        }
+    }
+
+    fn finish_search(&mut self) {
+        if let Some(s) = self.search.take() {
+            self.app.set_commands(s.wait());
+            self.ui.term.clear();
+        }
+    }
+}
+
+/// Writes the history, after backing up the current one.
+pub fn write_history(backups: &Backups, files: &Files, history: &[String]) -> Result<(), Error> {
+    backups.create(&files.history())?;
+    hstr::write_history(files, history)
+}
+
+/// The pasted text, on a single line. A trailing line break is most
+/// likely not meant to be searched for, and the others become spaces.
+fn pasted_query(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .trim_end_matches(&['\n', '\r'][..])
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+fn is_query_edit(user_input: &Key) -> bool {
+    match user_input {
+        Key::Char(ch) => ![
+            CTRL_B, CTRL_C, CTRL_E, CTRL_F, CTRL_G, TAB, ENTER, CTRL_K, CTRL_P, CTRL_T, CTRL_X,
+            CTRL_Z, ESC, CTRL_SLASH,
+        ]
+        .contains(ch),
+        key => *key == Key::Backspace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::fake::Screen;
+    use crate::terminal::fake::{keys, Fake};
+    use rstest::rstest;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    const HISTORY: [&str; 4] = ["ls -la", "cargo test", "git status", "git push"];
+    const FAVORITES: &str = ".config/hstr-rs/.bash_favorites";
+
+    /// A session in a temporary home, driven by scripted keys.
+    struct Harness {
+        session: Session,
+        screen: Rc<RefCell<Screen>>,
+        home: TempDir,
+    }
+
+    impl Harness {
+        fn new(history: &[&str]) -> Self {
+            let home = TempDir::new().unwrap();
+            fs::write(home.path().join(".bash_history"), history.join("\n") + "\n").unwrap();
+            let files = Files::under(home.path(), "bash");
+            let mut app = Application::new(files.clone(), home.path().join("cache"));
+            app.load_commands().unwrap();
+            let fake = Fake::new(10, 120);
+            let screen = fake.screen();
+            let mut ui = UserInterface::new(Box::new(fake));
+            ui.prompt = String::from("user@host$");
+            let backups = Backups::new(home.path().join("backups"), 5);
+            let session = Session::new(app, ui, backups, Watcher::new(&files).ok());
+            Self {
+                session,
+                screen,
+                home,
+            }
+        }
+
+        /// Plays the script up to the key that ends the session, if any.
+        fn play(&mut self, script: &str) -> Option<Action> {
+            keys(script)
+                .into_iter()
+                .find_map(|key| self.session.handle(Event::Key(key)))
+        }
+
+        fn read(&self, file: &str) -> Vec<String> {
+            let contents = fs::read_to_string(self.home.path().join(file)).unwrap_or_default();
+            contents.lines().map(String::from).collect()
+        }
+    }
+
+    fn insert(command: &str, run: bool) -> Option<Action> {
+        Some(Action::Insert {
+            command: command.to_string(),
+            run,
+        })
+    }
+
+    #[rstest(
+        script,
+        expected,
+        case("cargo<Tab>", insert("cargo test", false)),
+        case("cargo<Enter>", insert("cargo test", true)),
+        case("git s<BS>p<Enter>", insert("git push", true)),
+        case("svn<Enter><Tab>", None),
+        case("svn<Enter><Esc>", Some(Action::Quit)),
+        case("cargo<M-x><F1><Tab>", insert("cargo test", false)),
+        case("<PasteStart>cargo\n<PasteEnd><Tab>", insert("cargo test", false)),
+        case("<PasteStart>git\r\nst<PasteEnd><Enter>", insert("git status", true)),
+        case("git<Click 4,5><Enter>", insert("git status", true)),
+        case("git<Click 4,5><Click 5,5><Enter>", insert("git status", true)),
+        case("git<DoubleClick 4,5>", insert("git status", false)),
+        case("git<WheelDown><Tab>", insert("git status", false)),
+        case("git<WheelDown><WheelUp><Tab>", insert("git push", false)),
+        case("<M-2>", insert("git status", false)),
+        case("git<M-2>", insert("git status", false)),
+        case("git<M-3>", None),
+        case("<C-c>", Some(Action::Quit))
+    )]
+    fn action(script: &str, expected: Option<Action>) {
+        let mut harness = Harness::new(&HISTORY);
+        assert_eq!(harness.play(script), expected);
+    }
+
+    #[rstest(
+        script,
+        file,
+        expected,
+        case("cargo<C-f>", FAVORITES, vec!["cargo test"]),
+        case("cargo<C-f><C-/><Del>n", FAVORITES, vec!["cargo test"]),
+        case("cargo<C-f><C-/><Del>y", FAVORITES, vec![]),
+        case("cargo<C-f><C-z>", FAVORITES, vec![]),
+        case("ls<C-k>", ".config/hstr-rs/.bash_hidden", vec!["ls -la"]),
+        case("git st<C-b>", ".config/hstr-rs/.bash_blacklist", vec!["git status"]),
+        case("cargo<Del>y", ".bash_history", vec!["ls -la", "git status", "git push"]),
+        case("cargo<Del>n", ".bash_history", HISTORY.to_vec()),
+        case("cargo<Del><PasteStart>y<PasteEnd>n", ".bash_history", HISTORY.to_vec()),
+        case("cargo<Del><Click 3,5>y", ".bash_history", vec!["ls -la", "git status", "git push"]),
+        case("cargo<Del>y<C-z>", ".bash_history", HISTORY.to_vec()),
+        case("git<C-x>y", ".bash_history", vec!["ls -la", "cargo test"]),
+        case("git<C-x><Esc>", ".bash_history", HISTORY.to_vec()),
+        case(
+            "push<C-k><BS><BS><BS><BS>git<C-x>y",
+            ".bash_history",
+            vec!["ls -la", "cargo test", "git push"]
+        ),
+        case("push<C-f><C-/><C-x>y", ".bash_history", HISTORY.to_vec()),
+        case("push<C-f><C-/><C-x>y", FAVORITES, vec!["git push"])
+    )]
+    fn files(script: &str, file: &str, expected: Vec<&str>) {
+        let mut harness = Harness::new(&HISTORY);
+        harness.play(script);
+        assert_eq!(harness.read(file), expected);
+    }
+
+    #[rstest(
+        text,
+        expected,
+        case("git status", "git status"),
+        case("git status\n", "git status"),
+        case("make &&\r\nmake install\r\n", "make && make install"),
+        case("ls\t-la\u{7}", "ls -la"),
+        case("\n", "")
+    )]
+    fn pasted_query(text: &str, expected: &str) {
+        assert_eq!(super::pasted_query(text), expected);
+    }
+
+    #[test]
+    fn backup_before_deleting() {
+        let mut harness = Harness::new(&HISTORY);
+        harness.play("cargo<Del>y");
+        let backups = fs::read_dir(harness.home.path().join("backups")).unwrap();
+        assert_eq!(backups.count(), 1);
+    }
+
+    #[test]
+    fn prompt_waits_for_answer() {
+        let mut harness = Harness::new(&HISTORY);
+        harness.play("cargo<Del>");
+        assert_eq!(harness.session.timeout(), None);
+        harness.session.handle(Event::Tick);
+        assert!(harness.screen.borrow().line(1).contains("Delete"));
+        harness.play("n");
+        assert!(harness.screen.borrow().line(1).contains("Type to filter"));
+    }
+
+    #[rstest(
+        script,
+        expected,
+        case(
+            "",
+            "view:sorted (C-/) - regex:off (C-e) - case:insensitive (C-t) - 1/4"
+        ),
+        case(
+            "<Click 2,5><Click 2,30><Click 2,50>",
+            "view:favorites (C-/) - regex:on (C-e) - case:sensitive (C-t) - 0/0"
+        ),
+        case(
+            "<C-/><C-e><C-t>",
+            "view:favorites (C-/) - regex:on (C-e) - case:sensitive (C-t) - 0/0"
+        ),
+        case("<C-g><C-p>", "1/4 - blacklisted shown (C-g) - presenting (C-p)")
+    )]
+    fn status_line(script: &str, expected: &str) {
+        let mut harness = Harness::new(&HISTORY);
+        harness.play(script);
+        let status = harness.screen.borrow().line(2);
+        assert!(status.contains(expected), "{}", status);
+    }
+
+    #[rstest(
+        script,
+        expected,
+        case("<Tab>", insert("git push", false)),
+        case("<Up><Tab>", insert("git status", false)),
+        case("<Up><Down><PageUp><Tab>", insert("git push", false)),
+        case("<WheelUp><Tab>", insert("git status", false)),
+        case("<Click 5,5><Enter>", insert("git status", true)),
+        case("<DoubleClick 6,5>", insert("git push", false))
+    )]
+    fn bottom_up(script: &str, expected: Option<Action>) {
+        let mut harness = Harness::new(&HISTORY);
+        harness.session.ui.bottom_up = true;
+        assert_eq!(harness.play(script), expected);
+    }
+
+    #[rstest(
+        scrolling,
+        script,
+        expected,
+        case(false, "<PageDown><Down>", (2, 0, 1, "9/20")),
+        case(false, "<PageDown><Down><PageUp><PageUp>", (3, 0, 1, "16/20")),
+        case(false, "<Up>", (3, 0, 5, "20/20")),
+        case(false, "<End><Home>", (1, 0, 0, "1/20")),
+        case(false, "<F5>", (2, 0, 3, "11/20")),
+        case(true, "<Down><Down><Down><Down>", (1, 1, 3, "5/20")),
+        case(true, "<Down><Down><Down><Down><Up>", (1, 0, 3, "4/20")),
+        case(true, "<Up>", (1, 13, 6, "20/20")),
+        case(true, "<F5>", (1, 7, 3, "11/20")),
+        case(true, "<PageDown>", (1, 4, 3, "8/20")),
+        case(true, "<End><PageUp>", (1, 9, 3, "13/20")),
+        case(true, "<End><Home>", (1, 0, 0, "1/20"))
+    )]
+    fn moving(scrolling: bool, script: &str, expected: (i32, i32, i32, &str)) {
+        let history = (0..20).map(|i| format!("echo {}", i)).collect::<Vec<_>>();
+        let mut harness = Harness::new(&history.iter().map(|x| x.as_str()).collect::<Vec<_>>());
+        harness.session.ui.scrolling = scrolling;
+        harness.play(script);
+        let ui = &harness.session.ui;
+        assert_eq!(
+            (ui.page, ui.top, ui.selected),
+            (expected.0, expected.1, expected.2)
+        );
+        let status = harness.screen.borrow().line(2);
+        assert!(
+            status.contains(&format!("- {} -", expected.3)),
+            "{}",
+            status
+        );
+    }
+
+    #[test]
+    fn reload_on_tick() {
+        let mut harness = Harness::new(&HISTORY);
+        let history = harness.home.path().join(".bash_history");
+        let mut lines = harness.read(".bash_history");
+        lines.push(String::from("make -j4"));
+        fs::write(history, lines.join("\n") + "\n").unwrap();
+        harness.session.handle(Event::Tick);
+        assert_eq!(harness.play("make<Tab>"), insert("make -j4", false));
+    }
+
+    #[test]
+    fn redraw_on_tick_only_after_changes() {
+        let mut harness = Harness::new(&HISTORY);
+        harness.screen.borrow_mut().clear();
+        harness.session.handle(Event::Tick);
+        assert_eq!(harness.screen.borrow().line(1), "");
+        let history = harness.home.path().join(".bash_history");
+        fs::write(history, "make -j4\n").unwrap();
+        harness.session.handle(Event::Tick);
+        assert_eq!(harness.screen.borrow().line(3), "1 make -j4");
+    }
+}
@@ -0,0 +1,19 @@
+use libc::{ioctl, TIOCSTI};
+use std::env;
+
+/// Puts the command on the shell's command line, as if it was typed.
+pub fn echo(command: String) {
+    unsafe {
+        for byte in command.as_bytes() {
+            ioctl(0, TIOCSTI, byte);
+        }
+    }
+}
+
+pub fn get_shell_prompt() -> String {
+    format!(
+        "{}@{}$",
+        env::var("USER").unwrap_or_default(),
+        gethostname::gethostname().to_string_lossy()
+    )
+}
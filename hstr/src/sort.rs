@@ -1,20 +1,38 @@
+use itertools::Itertools;
 use std::cmp::{Eq, Reverse};
 use std::collections::HashMap;
 use std::hash::Hash;
 
-pub fn sort<T>(mut commands: Vec<T>) -> Vec<T>
+/// Ranks a whole history at once. The on-disk index in `cache` must
+/// always produce the same order as this.
+pub fn sort<T>(commands: Vec<T>) -> Vec<T>
 where
     T: Clone + Eq + Hash,
 {
     let freq_map = frequency_map(&commands);
     let pos_map = position_map(&commands);
-    commands.sort_by_key(|c| Reverse(pos_map.get(c).unwrap()));
-    commands.dedup();
-    commands.sort_by_key(|c| Reverse(freq_map.get(c).unwrap()));
+    rank(commands.into_iter().unique().collect(), &freq_map, &pos_map)
+}
+
+/// Orders unique commands by frequency, most recent first on ties.
+pub(crate) fn rank<T>(
+    mut commands: Vec<T>,
+    freq_map: &HashMap<T, usize>,
+    pos_map: &HashMap<T, usize>,
+) -> Vec<T>
+where
+    T: Clone + Eq + Hash,
+{
+    commands.sort_by_key(|c| {
+        (
+            Reverse(*freq_map.get(c).unwrap()),
+            Reverse(*pos_map.get(c).unwrap()),
+        )
+    });
     commands
 }
 
-fn frequency_map<T>(commands: &[T]) -> HashMap<T, usize>
+pub(crate) fn frequency_map<T>(commands: &[T]) -> HashMap<T, usize>
 where
     T: Clone + Eq + Hash,
 {
@@ -25,7 +43,7 @@ where
     map
 }
 
-fn position_map<T>(commands: &[T]) -> HashMap<T, usize>
+pub(crate) fn position_map<T>(commands: &[T]) -> HashMap<T, usize>
 where
     T: Clone + Eq + Hash,
 {
@@ -0,0 +1,72 @@
+use crate::error::Error;
+use crate::util::{home_dir, read_file, write_file};
+use std::path::{Path, PathBuf};
+
+/// Where a shell's history and lists are kept.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Files {
+    shell: String,
+    home: PathBuf,
+}
+
+impl Files {
+    /// The files of `shell` in the home directory.
+    pub fn new(shell: &str) -> Result<Self, Error> {
+        Ok(Self::under(&home_dir()?, shell))
+    }
+
+    /// The files of `shell` in `home`, laid out as in the home directory.
+    pub fn under(home: &Path, shell: &str) -> Self {
+        Self {
+            shell: shell.to_string(),
+            home: home.to_path_buf(),
+        }
+    }
+
+    pub fn shell(&self) -> &str {
+        &self.shell
+    }
+
+    pub fn history(&self) -> PathBuf {
+        self.home.join(format!(".{}_history", self.shell))
+    }
+}
+
+/* Lists of commands kept next to the history, one per line,
+ * in ~/.config/hstr-rs/.<shell>_<list>. */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum List {
+    Favorites,
+    Hidden,
+    Blacklist,
+}
+
+impl List {
+    pub fn path(self, files: &Files) -> PathBuf {
+        let name = match self {
+            List::Favorites => "favorites",
+            List::Hidden => "hidden",
+            List::Blacklist => "blacklist",
+        };
+        files
+            .home
+            .join(format!(".config/hstr-rs/.{}_{}", files.shell, name))
+    }
+
+    pub fn load(self, files: &Files) -> Result<Vec<String>, Error> {
+        read_file(&self.path(files))
+    }
+
+    pub fn save(self, files: &Files, commands: &[String]) -> Result<(), Error> {
+        write_file(&self.path(files), commands)
+    }
+}
+
+/// Every line of the history file, duplicates included.
+pub fn read_history(files: &Files) -> Result<Vec<String>, Error> {
+    read_file(&files.history())
+}
+
+pub fn write_history(files: &Files, history: &[String]) -> Result<(), Error> {
+    write_file(&files.history(), history)
+}
@@ -0,0 +1,386 @@
+use super::{Key, Read, Style, Terminal};
+use crate::config::Config;
+use ::crossterm::cursor::{self, MoveTo, MoveToPreviousLine, Show};
+use ::crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ::crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use ::crossterm::terminal::{
+    self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use ::crossterm::{execute, queue};
+use std::collections::VecDeque;
+use std::io::{self, stdout, BufWriter, Stdout, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const ESC: Key = Key::Char('\u{1b}');
+/// The most time between the clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The lines drawn on under the prompt, if any, for `restore` to clear
+/// from a panic hook, which has no hold of the terminal.
+static INLINE: Mutex<Option<Inline>> = Mutex::new(None);
+
+/* A terminal driven by escape sequences alone, without curses.
+ *
+ * Output is buffered until the next refresh. Unlike curses, nothing keeps
+ * track of what is on screen, so everything printed is sent as is.
+ *
+ * It draws on the alternate screen, or with a height in the config, on
+ * that many lines under the prompt, which it clears when done.
+ */
+pub struct Crossterm {
+    out: BufWriter<Stdout>,
+    size: (u16, u16),
+    cursor: (u16, u16),
+    escape_timeout: Duration,
+    /// An event read too early, while looking for the key after an ESC.
+    pending: Option<Event>,
+    /// The rest of a paste, which crossterm reads all at once.
+    pasted: VecDeque<Key>,
+    /// When and where the last click was, to tell double clicks,
+    /// which crossterm doesn't report.
+    last_click: Option<(Instant, Key)>,
+    inline: Option<Inline>,
+}
+
+/// The lines drawn on under the prompt.
+#[derive(Clone, Copy)]
+struct Inline {
+    height: u16,
+    /// The screen line of the first one.
+    top: u16,
+    /// Where the cursor goes back to when done.
+    cursor: (u16, u16),
+}
+
+impl Inline {
+    /* Makes room under the cursor, starting on the next line unless the
+     * cursor is at the start of one. The screen scrolls up if need be,
+     * so where the room is only shows once the cursor got there. */
+    fn reserve(out: &mut impl Write, height: u16) -> io::Result<Self> {
+        let (col, _) = cursor::position()?;
+        if col > 0 {
+            queue!(out, Print("\r\n"))?;
+        }
+        if height > 1 {
+            let newlines = "\r\n".repeat(height as usize - 1);
+            queue!(out, Print(newlines), MoveToPreviousLine(height - 1))?;
+        }
+        out.flush()?;
+        let (_, top) = cursor::position()?;
+        let cursor = match col {
+            0 => (0, top),
+            col => (col, top.saturating_sub(1)),
+        };
+        Ok(Self {
+            height,
+            top,
+            cursor,
+        })
+    }
+}
+
+impl Crossterm {
+    pub fn new(config: &Config) -> Result<Self, std::io::Error> {
+        enable_raw_mode()?;
+        let mut out = BufWriter::new(stdout());
+        let (cols, lines) = terminal::size()?;
+        let inline = match config.height {
+            Some(height) => {
+                let inline = Inline::reserve(&mut out, height.min(lines))?;
+                execute!(out, MoveTo(0, inline.top), Clear(ClearType::FromCursorDown))?;
+                remember(Some(inline));
+                Some(inline)
+            }
+            None => {
+                execute!(out, EnterAlternateScreen, Clear(ClearType::All))?;
+                None
+            }
+        };
+        execute!(out, EnableBracketedPaste)?;
+        if config.mouse {
+            execute!(out, EnableMouseCapture)?;
+        }
+        Ok(Self {
+            out,
+            size: (cols, inline.as_ref().map_or(lines, |i| i.height)),
+            cursor: (0, 0),
+            escape_timeout: config.escape_timeout,
+            pending: None,
+            pasted: VecDeque::new(),
+            last_click: None,
+            inline,
+        })
+    }
+
+    /// The screen line drawn on as line 0.
+    fn top(&self) -> u16 {
+        self.inline.as_ref().map_or(0, |i| i.top)
+    }
+
+    fn read_event(&mut self, timeout: Option<Duration>) -> Option<Event> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+        if let Some(timeout) = timeout {
+            if !event::poll(timeout).ok()? {
+                return None;
+            }
+        }
+        event::read().ok()
+    }
+
+    fn key_of(&mut self, event: Event) -> Option<Key> {
+        match event {
+            Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) => key(code, modifiers),
+            Event::Resize(cols, lines) => {
+                self.size = (cols, lines);
+                if let Some(inline) = &mut self.inline {
+                    inline.height = inline.height.min(lines);
+                    inline.top = inline.top.min(lines - inline.height);
+                    self.size.1 = inline.height;
+                    remember(Some(*inline));
+                }
+                Some(Key::Resize)
+            }
+            Event::Mouse(MouseEvent {
+                kind, column, row, ..
+            }) => self.mouse(kind, row as i32 - self.top() as i32, column as i32),
+            Event::Paste(text) => {
+                self.pasted.extend(text.chars().map(Key::Char));
+                self.pasted.push_back(Key::PasteEnd);
+                Some(Key::PasteStart)
+            }
+            _ => None,
+        }
+    }
+
+    fn mouse(&mut self, kind: MouseEventKind, line: i32, col: i32) -> Option<Key> {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let click = Key::Click { line, col };
+                let double = matches!(
+                    self.last_click,
+                    Some((at, last)) if last == click && at.elapsed() <= DOUBLE_CLICK
+                );
+                if double {
+                    self.last_click = None;
+                    Some(Key::DoubleClick { line, col })
+                } else {
+                    self.last_click = Some((Instant::now(), click));
+                    Some(click)
+                }
+            }
+            MouseEventKind::ScrollUp => Some(Key::WheelUp),
+            MouseEventKind::ScrollDown => Some(Key::WheelDown),
+            _ => None,
+        }
+    }
+}
+
+impl Terminal for Crossterm {
+    fn lines(&self) -> i32 {
+        self.size.1 as i32
+    }
+
+    fn cols(&self) -> i32 {
+        self.size.0 as i32
+    }
+
+    fn clear(&mut self) {
+        let _ = match &self.inline {
+            Some(inline) => queue!(
+                self.out,
+                MoveTo(0, inline.top),
+                Clear(ClearType::FromCursorDown)
+            ),
+            None => queue!(self.out, Clear(ClearType::All)),
+        };
+    }
+
+    fn print(&mut self, y: i32, x: i32, text: &str, style: Style) {
+        if y < 0 || y >= self.lines() || x < 0 || x >= self.cols() {
+            return;
+        }
+        let text = text
+            .chars()
+            .take((self.cols() - x) as usize)
+            .collect::<String>();
+        let (foreground, background, bold) = colors(style);
+        let top = self.top();
+        let _ = queue!(self.out, MoveTo(x as u16, y as u16 + top));
+        if let Some(color) = foreground {
+            let _ = queue!(self.out, SetForegroundColor(color));
+        }
+        if let Some(color) = background {
+            let _ = queue!(self.out, SetBackgroundColor(color));
+        }
+        if bold {
+            let _ = queue!(self.out, SetAttribute(Attribute::Bold));
+        }
+        let _ = queue!(
+            self.out,
+            Print(&text),
+            SetAttribute(Attribute::Reset),
+            ResetColor
+        );
+        self.cursor = (x as u16 + text.chars().count() as u16, y as u16);
+    }
+
+    fn refresh(&mut self) {
+        let (x, y) = (self.cursor.0, self.cursor.1 + self.top());
+        let _ = queue!(self.out, MoveTo(x, y));
+        let _ = self.out.flush();
+    }
+
+    fn read_key(&mut self, timeout: Option<Duration>) -> Read {
+        if let Some(key) = self.pasted.pop_front() {
+            return Read::Key(key);
+        }
+        self.refresh();
+        let key = match self.read_event(timeout).map(|e| self.key_of(e)) {
+            Some(Some(key)) => key,
+            Some(None) => return Read::Ignored,
+            None => return Read::Timeout,
+        };
+        if key != ESC {
+            return Read::Key(key);
+        }
+        /* crossterm reads ESC and the key after it as Alt and that key
+         * when both come in at once, which they may not, e.g. over ssh. */
+        match self.read_event(Some(self.escape_timeout)) {
+            Some(Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press,
+                ..
+            })) => Read::Key(Key::Alt(ch)),
+            event => {
+                self.pending = event;
+                Read::Key(key)
+            }
+        }
+    }
+
+    fn end(&mut self) {
+        remember(None);
+        leave(&mut self.out, self.inline);
+    }
+}
+
+fn remember(inline: Option<Inline>) {
+    *INLINE.lock().unwrap_or_else(|e| e.into_inner()) = inline;
+}
+
+/// Clears the lines drawn on under the prompt and puts the cursor back
+/// where it was, or leaves the alternate screen.
+fn leave(out: &mut impl Write, inline: Option<Inline>) {
+    let _ = execute!(out, DisableMouseCapture, DisableBracketedPaste);
+    let _ = match inline {
+        Some(inline) => execute!(
+            out,
+            MoveTo(0, inline.top),
+            Clear(ClearType::FromCursorDown),
+            MoveTo(inline.cursor.0, inline.cursor.1),
+            Show
+        ),
+        None => execute!(out, LeaveAlternateScreen, Show),
+    };
+    let _ = disable_raw_mode();
+}
+
+pub fn restore() {
+    let inline = INLINE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    leave(&mut stdout(), inline);
+}
+
+/// Maps a key to what ncurses reports for it in raw mode.
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<Key> {
+    if modifiers.contains(KeyModifiers::ALT) {
+        return match code {
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Alt(c)),
+            _ => None,
+        };
+    }
+    match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => control(c).map(Key::Char),
+        KeyCode::Char(c) => Some(Key::Char(c)),
+        KeyCode::Enter => Some(Key::Char('\n')),
+        KeyCode::Tab => Some(Key::Char('\t')),
+        KeyCode::Esc => Some(Key::Char('\u{1b}')),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::PageUp => Some(Key::PageUp),
+        KeyCode::PageDown => Some(Key::PageDown),
+        KeyCode::Home => Some(Key::Home),
+        KeyCode::End => Some(Key::End),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Delete => Some(Key::Delete),
+        KeyCode::F(n) => Some(Key::F(n)),
+        _ => None,
+    }
+}
+
+/* The control character typed with Ctrl and the given key. Terminals
+ * send C-4 to C-7 for the control characters past C-z, and crossterm
+ * reports C-/ as C-7. */
+fn control(c: char) -> Option<char> {
+    match c {
+        'a'..='z' | '@'..='_' => Some((c as u8 & 0x1f) as char),
+        '4'..='7' => Some((c as u8 - b'4' + 0x1c) as char),
+        '/' => Some('\u{1f}'),
+        _ => None,
+    }
+}
+
+/// The colours of the ncurses colour pairs, where white is ANSI white,
+/// which crossterm calls grey.
+fn colors(style: Style) -> (Option<Color>, Option<Color>, bool) {
+    match style {
+        Style::Normal => (None, None, false),
+        Style::Bold => (None, None, true),
+        Style::Selected => (Some(Color::Grey), Some(Color::DarkGreen), false),
+        Style::Status => (Some(Color::Black), Some(Color::Grey), false),
+        Style::Favorite => (Some(Color::DarkCyan), Some(Color::Black), false),
+        Style::Highlight => (Some(Color::DarkRed), Some(Color::Black), true),
+        Style::Alert => (Some(Color::Grey), Some(Color::DarkRed), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        code,
+        modifiers,
+        expected,
+        case(KeyCode::Char('a'), KeyModifiers::NONE, Some(Key::Char('a'))),
+        case(KeyCode::Char('f'), KeyModifiers::CONTROL, Some(Key::Char('\u{6}'))),
+        case(KeyCode::Char('7'), KeyModifiers::CONTROL, Some(Key::Char('\u{1f}'))),
+        case(KeyCode::Char('/'), KeyModifiers::CONTROL, Some(Key::Char('\u{1f}'))),
+        case(KeyCode::Enter, KeyModifiers::NONE, Some(Key::Char('\n'))),
+        case(KeyCode::Esc, KeyModifiers::NONE, Some(Key::Char('\u{1b}'))),
+        case(KeyCode::Delete, KeyModifiers::NONE, Some(Key::Delete)),
+        case(KeyCode::Char('x'), KeyModifiers::ALT, Some(Key::Alt('x'))),
+        case(KeyCode::Char('1'), KeyModifiers::ALT, Some(Key::Alt('1'))),
+        case(KeyCode::Up, KeyModifiers::ALT, None),
+        case(KeyCode::F(1), KeyModifiers::NONE, Some(Key::F(1))),
+        case(KeyCode::Insert, KeyModifiers::NONE, None)
+    )]
+    fn key(code: KeyCode, modifiers: KeyModifiers, expected: Option<Key>) {
+        assert_eq!(super::key(code, modifiers), expected);
+    }
+}
@@ -0,0 +1,39 @@
+use super::Key;
+
+/* Tests draw on the same virtual screen as replays. */
+pub use super::headless::{Headless as Fake, Screen};
+
+/// Turns a script such as `git<Down><C-f><Esc>` into keys.
+pub fn keys(script: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut chars = script.chars();
+    while let Some(ch) = chars.next() {
+        let mut token = ch.to_string();
+        if ch == '<' {
+            token.extend(chars.by_ref().take_while(|&c| c != '>'));
+            token.push('>');
+        }
+        keys.push(token.parse().unwrap());
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            super::keys("g<lt><Down><C-f><C-/><Esc>"),
+            vec![
+                Key::Char('g'),
+                Key::Char('<'),
+                Key::Down,
+                Key::Char('\u{6}'),
+                Key::Char('\u{1f}'),
+                Key::Char('\u{1b}'),
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use super::{Read, Style, Terminal};
+use std::cell::RefCell;
+use std::fmt;
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Draws on a screen kept in memory, for replaying a recording with no
+/// terminal to show it on, and for tests. The caller keeps a handle to
+/// the screen, to resize it and to read it back.
+pub struct Headless {
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl Headless {
+    pub fn new(lines: i32, cols: i32) -> Self {
+        Self {
+            screen: Rc::new(RefCell::new(Screen::new(lines, cols))),
+        }
+    }
+
+    pub fn screen(&self) -> Rc<RefCell<Screen>> {
+        Rc::clone(&self.screen)
+    }
+}
+
+impl Default for Headless {
+    fn default() -> Self {
+        Self::new(10, 80)
+    }
+}
+
+/// The character each style shows up as under the text it was drawn in.
+pub fn attribute(style: Style) -> char {
+    match style {
+        Style::Normal => ' ',
+        Style::Bold => 'b',
+        Style::Selected => 's',
+        Style::Status => 't',
+        Style::Favorite => 'f',
+        Style::Highlight => 'h',
+        Style::Alert => 'a',
+    }
+}
+
+/// A grid of characters, each with the attribute it was drawn in.
+pub struct Screen {
+    lines: i32,
+    cols: i32,
+    cells: Vec<(char, char)>,
+}
+
+impl Screen {
+    fn new(lines: i32, cols: i32) -> Self {
+        Self {
+            lines,
+            cols,
+            cells: vec![(' ', ' '); (lines.max(0) * cols.max(0)) as usize],
+        }
+    }
+
+    /// Changes the size of the screen, which comes back blank.
+    pub fn resize(&mut self, lines: i32, cols: i32) {
+        *self = Self::new(lines, cols);
+    }
+
+    /// Blanks every cell.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|c| *c = (' ', ' '));
+    }
+
+    fn row(&self, y: i32) -> &[(char, char)] {
+        let start = (y * self.cols) as usize;
+        &self.cells[start..start + self.cols as usize]
+    }
+
+    /// The text of line `y`, without trailing blanks.
+    pub fn line(&self, y: i32) -> String {
+        let line = self.row(y).iter().map(|c| c.0).collect::<String>();
+        line.trim_end().to_string()
+    }
+
+    /// The attributes of line `y`, one character per cell,
+    /// without trailing blanks.
+    pub fn attrs(&self, y: i32) -> String {
+        let attrs = self.row(y).iter().map(|c| c.1).collect::<String>();
+        attrs.trim_end().to_string()
+    }
+
+    /// Compares the screen with the golden file `dir/name.snap`.
+    ///
+    /// If `UPDATE_SNAPSHOTS` is set, the file is written instead. A
+    /// missing file fails the test otherwise, so that a snapshot that was
+    /// never committed cannot pass unnoticed.
+    #[cfg(test)]
+    pub fn assert_snapshot(&self, dir: impl AsRef<Path>, name: &str) {
+        let path = dir.as_ref().join(format!("{}.snap", name));
+        let actual = self.to_string();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(dir.as_ref()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "{} is missing, run with UPDATE_SNAPSHOTS=1 to write it\n--- actual\n{}",
+                path.display(),
+                actual
+            )
+        });
+        assert!(
+            expected == actual,
+            "screen does not match {}\n--- expected\n{}\n--- actual\n{}",
+            path.display(),
+            expected,
+            actual
+        );
+    }
+}
+
+/* Each line is printed twice: its text, then its attributes under it,
+ * both without trailing blanks. */
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.lines {
+            writeln!(f, "|{}", self.line(y))?;
+            writeln!(f, "|{}", self.attrs(y))?;
+        }
+        Ok(())
+    }
+}
+
+impl Terminal for Headless {
+    fn lines(&self) -> i32 {
+        self.screen.borrow().lines
+    }
+
+    fn cols(&self) -> i32 {
+        self.screen.borrow().cols
+    }
+
+    fn clear(&mut self) {
+        self.screen.borrow_mut().clear();
+    }
+
+    fn print(&mut self, y: i32, x: i32, text: &str, style: Style) {
+        let mut screen = self.screen.borrow_mut();
+        if y < 0 || y >= screen.lines || x < 0 || x >= screen.cols {
+            return;
+        }
+        let start = (y * screen.cols + x) as usize;
+        let width = (screen.cols - x) as usize;
+        for (cell, ch) in screen.cells[start..start + width]
+            .iter_mut()
+            .zip(text.chars())
+        {
+            *cell = (ch, attribute(style));
+        }
+    }
+
+    fn refresh(&mut self) {}
+
+    fn read_key(&mut self, _timeout: Option<Duration>) -> Read {
+        Read::Timeout
+    }
+
+    fn end(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let mut headless = Headless::new(2, 8);
+        headless.print(0, 2, "plain", Style::Normal);
+        headless.print(1, 4, "clipped", Style::Highlight);
+        headless.print(2, 0, "outside", Style::Normal);
+        let screen = headless.screen();
+        assert_eq!(
+            screen.borrow().to_string(),
+            "|  plain\n|\n|    clip\n|    hhhh\n"
+        );
+        screen.borrow_mut().resize(1, 4);
+        assert_eq!(headless.lines(), 1);
+        assert_eq!(screen.borrow().to_string(), "|\n|\n");
+    }
+}
@@ -0,0 +1,366 @@
+use crate::config::Config;
+use hstr::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(feature = "crossterm")]
+mod crossterm;
+#[cfg(test)]
+pub mod fake;
+pub mod headless;
+#[cfg(feature = "ncurses")]
+mod ncurses;
+
+#[cfg(not(any(feature = "ncurses", feature = "crossterm")))]
+compile_error!("hstr-rs needs at least one of the ncurses and crossterm features");
+
+/// How text is drawn. Each style but `Normal` and `Bold` is a colour
+/// pair in ncurses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Style {
+    Normal,
+    Bold,
+    /// White on green, for the selected entry.
+    Selected,
+    /// Black on white, for the status line.
+    Status,
+    /// Cyan, for favorites.
+    Favorite,
+    /// Bold red, for matches and secrets.
+    Highlight,
+    /// White on red, for prompts and errors.
+    Alert,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Key {
+    /// A character as typed, control characters included.
+    Char(char),
+    /// A character typed with Alt, or right after ESC.
+    Alt(char),
+    /// A function key, from F1.
+    F(u8),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Backspace,
+    Delete,
+    Resize,
+    /// Everything between these two was pasted.
+    PasteStart,
+    PasteEnd,
+    /// A left click on the given screen cell.
+    Click {
+        line: i32,
+        col: i32,
+    },
+    DoubleClick {
+        line: i32,
+        col: i32,
+    },
+    WheelUp,
+    WheelDown,
+}
+
+impl Key {
+    pub fn is_mouse(&self) -> bool {
+        matches!(
+            self,
+            Key::Click { .. } | Key::DoubleClick { .. } | Key::WheelUp | Key::WheelDown
+        )
+    }
+}
+
+/* Keys are written the way they are typed, with named keys between
+ * `<>`, e.g. `git<Down><C-f>`. Recordings and test scripts use this. */
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Char('<') => write!(f, "<lt>"),
+            Key::Char(' ') => write!(f, "<Space>"),
+            Key::Char('\t') => write!(f, "<Tab>"),
+            Key::Char('\n') => write!(f, "<Enter>"),
+            Key::Char('\u{1b}') => write!(f, "<Esc>"),
+            Key::Char('\u{1f}') => write!(f, "<C-/>"),
+            Key::Char(ch @ '\u{1}'..='\u{1a}') => {
+                write!(f, "<C-{}>", (*ch as u8 - 1 + b'a') as char)
+            }
+            Key::Char(ch) if ch.is_control() => write!(f, "<U+{:04X}>", *ch as u32),
+            Key::Char(ch) => write!(f, "{}", ch),
+            Key::Alt(' ') => write!(f, "<M-Space>"),
+            Key::Alt(ch) => write!(f, "<M-{}>", ch),
+            Key::F(n) => write!(f, "<F{}>", n),
+            Key::Up => write!(f, "<Up>"),
+            Key::Down => write!(f, "<Down>"),
+            Key::PageUp => write!(f, "<PageUp>"),
+            Key::PageDown => write!(f, "<PageDown>"),
+            Key::Home => write!(f, "<Home>"),
+            Key::End => write!(f, "<End>"),
+            Key::Backspace => write!(f, "<BS>"),
+            Key::Delete => write!(f, "<Del>"),
+            Key::Resize => write!(f, "<Resize>"),
+            Key::PasteStart => write!(f, "<PasteStart>"),
+            Key::PasteEnd => write!(f, "<PasteEnd>"),
+            Key::Click { line, col } => write!(f, "<Click {},{}>", line, col),
+            Key::DoubleClick { line, col } => write!(f, "<DoubleClick {},{}>", line, col),
+            Key::WheelUp => write!(f, "<WheelUp>"),
+            Key::WheelDown => write!(f, "<WheelDown>"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Parses a single key, as written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(ch));
+        }
+        let name = match s.strip_prefix('<').and_then(|x| x.strip_suffix('>')) {
+            Some(name) => name,
+            None => return Err(format!("not a key: {}", s)),
+        };
+        let key = match name {
+            "lt" => Key::Char('<'),
+            "Space" => Key::Char(' '),
+            "Tab" => Key::Char('\t'),
+            "Enter" => Key::Char('\n'),
+            "Esc" => Key::Char('\u{1b}'),
+            "C-/" => Key::Char('\u{1f}'),
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "BS" => Key::Backspace,
+            "Del" => Key::Delete,
+            "Resize" => Key::Resize,
+            "PasteStart" => Key::PasteStart,
+            "PasteEnd" => Key::PasteEnd,
+            "WheelUp" => Key::WheelUp,
+            "WheelDown" => Key::WheelDown,
+            "M-Space" => Key::Alt(' '),
+            name => parse_prefixed(name).ok_or_else(|| format!("unknown key: {}", s))?,
+        };
+        Ok(key)
+    }
+}
+
+/// Parses the names of keys that take a parameter, e.g. `C-f`,
+/// `M-f`, `F1`, `U+007F` or `Click 3,10`.
+fn parse_prefixed(name: &str) -> Option<Key> {
+    if let Some((button, cell)) = name.split_once(' ') {
+        let (line, col) = cell.split_once(',')?;
+        let (line, col) = (line.parse().ok()?, col.parse().ok()?);
+        return match button {
+            "Click" => Some(Key::Click { line, col }),
+            "DoubleClick" => Some(Key::DoubleClick { line, col }),
+            _ => None,
+        };
+    }
+    if let Some(letter) = name.strip_prefix("C-") {
+        return match letter.as_bytes() {
+            &[letter] if letter.is_ascii_lowercase() => {
+                Some(Key::Char((letter - b'a' + 1) as char))
+            }
+            _ => None,
+        };
+    }
+    if let Some(ch) = name.strip_prefix("M-") {
+        let mut chars = ch.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(ch), None) if !ch.is_control() => Some(Key::Alt(ch)),
+            _ => None,
+        };
+    }
+    if let Some(n) = name.strip_prefix('F') {
+        return n.parse().ok().filter(|&n| n > 0).map(Key::F);
+    }
+    let code = name.strip_prefix("U+")?;
+    u32::from_str_radix(code, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+        .map(Key::Char)
+}
+
+/// What waiting for a key came to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Read {
+    Key(Key),
+    /// Input that stands for no key, e.g. an escape sequence for a key
+    /// with no use here, or the mouse moving.
+    Ignored,
+    /// Nothing came in time.
+    Timeout,
+}
+
+/// The drawing and input operations `UserInterface` needs.
+pub trait Terminal {
+    fn lines(&self) -> i32;
+    fn cols(&self) -> i32;
+    fn clear(&mut self);
+    /// Prints `text` from line `y`, column `x`, cutting it at the right
+    /// edge of the screen rather than wrapping it onto the next line.
+    /// Text starting off the screen isn't printed at all.
+    fn print(&mut self, y: i32, x: i32, text: &str, style: Style);
+    /// Makes everything printed since the last refresh visible.
+    fn refresh(&mut self);
+    /// Waits for a key for at most `timeout`, or for as long as it takes
+    /// if there is none.
+    fn read_key(&mut self, timeout: Option<Duration>) -> Read;
+    /// Gives the terminal back to the shell.
+    fn end(&mut self);
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    #[cfg(feature = "ncurses")]
+    Ncurses,
+    #[cfg(feature = "crossterm")]
+    Crossterm,
+}
+
+impl Default for Backend {
+    #[cfg(feature = "ncurses")]
+    fn default() -> Self {
+        Backend::Ncurses
+    }
+
+    #[cfg(not(feature = "ncurses"))]
+    fn default() -> Self {
+        Backend::Crossterm
+    }
+}
+
+impl Backend {
+    /// The backend set in the config, if it was built in, or the
+    /// default one. Drawing under the prompt takes crossterm, as ncurses
+    /// always takes over the whole screen.
+    pub fn from_config(config: &Config) -> Self {
+        match config.backend.as_deref() {
+            #[cfg(feature = "crossterm")]
+            _ if config.height.is_some() => Backend::Crossterm,
+            #[cfg(feature = "ncurses")]
+            Some("ncurses") => Backend::Ncurses,
+            #[cfg(feature = "crossterm")]
+            Some("crossterm") => Backend::Crossterm,
+            _ => Self::default(),
+        }
+    }
+
+    /// What of the config `from_config` can't follow, if anything:
+    /// `height` takes crossterm, so it overrides `backend = ncurses`,
+    /// and it is ignored if crossterm wasn't built in.
+    pub fn conflict(config: &Config) -> Option<&'static str> {
+        config.height?;
+        if cfg!(not(feature = "crossterm")) {
+            return Some("height ignored, it takes crossterm, which was not built in");
+        }
+        match config.backend.as_deref() {
+            Some("ncurses") => Some("backend = ncurses ignored, height takes crossterm"),
+            _ => None,
+        }
+    }
+
+    /// Opens the terminal, where an ESC followed by another key within
+    /// `config.escape_timeout` is read as Alt and that key, and where
+    /// the mouse is read if `config.mouse` is set.
+    pub fn open(self, config: &Config) -> Result<Box<dyn Terminal>, Error> {
+        match self {
+            #[cfg(feature = "ncurses")]
+            Backend::Ncurses => Ok(Box::new(ncurses::Ncurses::new(config))),
+            #[cfg(feature = "crossterm")]
+            Backend::Crossterm => Ok(Box::new(crossterm::Crossterm::new(config)?)),
+        }
+    }
+
+    /// Gives the terminal back to the shell from wherever we are,
+    /// e.g. from a panic hook.
+    pub fn restore(self) {
+        match self {
+            #[cfg(feature = "ncurses")]
+            Backend::Ncurses => ncurses::restore(),
+            #[cfg(feature = "crossterm")]
+            Backend::Crossterm => crossterm::restore(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        key,
+        text,
+        case(Key::Char('g'), "g"),
+        case(Key::Char('é'), "é"),
+        case(Key::Char('<'), "<lt>"),
+        case(Key::Char(' '), "<Space>"),
+        case(Key::Char('\n'), "<Enter>"),
+        case(Key::Char('\u{6}'), "<C-f>"),
+        case(Key::Char('\u{1f}'), "<C-/>"),
+        case(Key::Char('\u{7f}'), "<U+007F>"),
+        case(Key::Alt('x'), "<M-x>"),
+        case(Key::Alt('1'), "<M-1>"),
+        case(Key::Alt(' '), "<M-Space>"),
+        case(Key::F(12), "<F12>"),
+        case(Key::PageDown, "<PageDown>"),
+        case(Key::End, "<End>"),
+        case(Key::Resize, "<Resize>"),
+        case(Key::PasteEnd, "<PasteEnd>"),
+        case(Key::Click { line: 3, col: 10 }, "<Click 3,10>"),
+        case(Key::DoubleClick { line: 0, col: 1 }, "<DoubleClick 0,1>"),
+        case(Key::WheelDown, "<WheelDown>")
+    )]
+    fn notation(key: Key, text: &str) {
+        assert_eq!(key.to_string(), text);
+        assert_eq!(text.parse::<Key>(), Ok(key));
+    }
+
+    #[rstest(
+        text,
+        case(""),
+        case("gi"),
+        case("<F0>"),
+        case("<Fx>"),
+        case("<C-F>"),
+        case("<M-xy>"),
+        case("<Click 3>"),
+        case("<Tap 3,10>"),
+        case("<U+D800>")
+    )]
+    fn bad_notation(text: &str) {
+        assert!(text.parse::<Key>().is_err());
+    }
+
+    #[cfg(all(feature = "ncurses", feature = "crossterm"))]
+    #[rstest(
+        backend,
+        height,
+        expected,
+        conflict,
+        case(None, None, Backend::Ncurses, false),
+        case(Some("crossterm"), None, Backend::Crossterm, false),
+        case(None, Some(10), Backend::Crossterm, false),
+        case(Some("crossterm"), Some(10), Backend::Crossterm, false),
+        case(Some("ncurses"), Some(10), Backend::Crossterm, true)
+    )]
+    fn from_config(backend: Option<&str>, height: Option<u16>, expected: Backend, conflict: bool) {
+        let config = Config {
+            backend: backend.map(String::from),
+            height,
+            ..Config::default()
+        };
+        assert_eq!(Backend::from_config(&config), expected);
+        assert_eq!(Backend::conflict(&config).is_some(), conflict);
+    }
+}
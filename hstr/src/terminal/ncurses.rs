@@ -0,0 +1,198 @@
+use super::{Key, Read, Style, Terminal};
+use crate::config::Config;
+use ::ncurses as nc;
+use std::io::{stdout, Write};
+use std::time::Duration;
+
+const ESC: char = '\u{1b}';
+
+pub struct Ncurses;
+
+impl Ncurses {
+    pub fn new(config: &Config) -> Self {
+        nc::set_escdelay(config.escape_timeout.as_millis() as i32);
+        nc::initscr();
+        nc::raw();
+        nc::noecho();
+        nc::keypad(nc::stdscr(), true);
+        nc::start_color();
+        nc::init_pair(2, nc::COLOR_WHITE, nc::COLOR_GREEN); // selected
+        nc::init_pair(3, nc::COLOR_BLACK, nc::COLOR_WHITE); // status
+        nc::init_pair(4, nc::COLOR_CYAN, nc::COLOR_BLACK); // favorites
+        nc::init_pair(5, nc::COLOR_RED, nc::COLOR_BLACK); // highlight
+        nc::init_pair(6, nc::COLOR_WHITE, nc::COLOR_RED); // alert
+        set_bracketed_paste(true);
+        if config.mouse {
+            let buttons = nc::BUTTON1_CLICKED
+                | nc::BUTTON1_DOUBLE_CLICKED
+                | nc::BUTTON4_PRESSED
+                | nc::BUTTON5_PRESSED;
+            nc::mousemask(buttons as nc::mmask_t, None);
+        }
+        Self
+    }
+}
+
+impl Terminal for Ncurses {
+    fn lines(&self) -> i32 {
+        nc::LINES()
+    }
+
+    fn cols(&self) -> i32 {
+        nc::COLS()
+    }
+
+    fn clear(&mut self) {
+        nc::clear();
+    }
+
+    /* ncurses would wrap the rest of the text onto the next line, so it
+     * is cut here. mvaddnstr counts bytes, not characters. */
+    fn print(&mut self, y: i32, x: i32, text: &str, style: Style) {
+        let text = text
+            .chars()
+            .take((nc::COLS() - x).max(0) as usize)
+            .collect::<String>();
+        let attributes = attributes(style);
+        nc::attron(attributes);
+        nc::mvaddstr(y, x, &text);
+        nc::attroff(attributes);
+    }
+
+    fn refresh(&mut self) {
+        nc::refresh();
+    }
+
+    fn read_key(&mut self, timeout: Option<Duration>) -> Read {
+        nc::timeout(timeout.map_or(-1, |t| t.as_millis() as i32));
+        let key = match read() {
+            Read::Key(key) => key,
+            other => return other,
+        };
+        if key != Key::Char(ESC) {
+            return Read::Key(key);
+        }
+        /* ncurses has already waited ESCDELAY for the rest of a sequence
+         * it doesn't know, so whatever follows the ESC is there by now. */
+        nc::timeout(0);
+        match read() {
+            Read::Timeout => Read::Key(key),
+            Read::Key(Key::Char(ESC)) => {
+                /* The second ESC may start a sequence of its own. */
+                nc::unget_wch(ESC as u32);
+                Read::Key(key)
+            }
+            Read::Key(Key::Char(introducer @ '[')) | Read::Key(Key::Char(introducer @ 'O')) => {
+                match read_sequence(introducer).as_str() {
+                    "[200~" => Read::Key(Key::PasteStart),
+                    "[201~" => Read::Key(Key::PasteEnd),
+                    _ => Read::Ignored,
+                }
+            }
+            Read::Key(Key::Char(ch)) if !ch.is_control() => Read::Key(Key::Alt(ch)),
+            _ => Read::Ignored,
+        }
+    }
+
+    fn end(&mut self) {
+        nc::clear();
+        nc::refresh();
+        nc::doupdate();
+        set_bracketed_paste(false);
+        nc::endwin();
+    }
+}
+
+fn read() -> Read {
+    let key = match nc::get_wch() {
+        None => return Read::Timeout,
+        Some(nc::WchResult::Char(ch)) => std::char::from_u32(ch).map(Key::Char),
+        Some(nc::WchResult::KeyCode(code)) => key(code),
+    };
+    key.map_or(Read::Ignored, Read::Key)
+}
+
+fn key(code: i32) -> Option<Key> {
+    match code {
+        nc::KEY_UP => Some(Key::Up),
+        nc::KEY_DOWN => Some(Key::Down),
+        nc::KEY_PPAGE => Some(Key::PageUp),
+        nc::KEY_NPAGE => Some(Key::PageDown),
+        nc::KEY_HOME => Some(Key::Home),
+        nc::KEY_END => Some(Key::End),
+        nc::KEY_BACKSPACE => Some(Key::Backspace),
+        nc::KEY_DC => Some(Key::Delete),
+        nc::KEY_RESIZE => Some(Key::Resize),
+        nc::KEY_MOUSE => mouse(),
+        code if code > nc::KEY_F0 && code <= nc::KEY_F0 + 63 => {
+            Some(Key::F((code - nc::KEY_F0) as u8))
+        }
+        _ => None,
+    }
+}
+
+fn mouse() -> Option<Key> {
+    let mut event = nc::MEVENT {
+        id: 0,
+        x: 0,
+        y: 0,
+        z: 0,
+        bstate: 0,
+    };
+    if nc::getmouse(&mut event) != nc::OK {
+        return None;
+    }
+    let (line, col) = (event.y, event.x);
+    let pressed = |button: i32| event.bstate & button as nc::mmask_t != 0;
+    if pressed(nc::BUTTON1_DOUBLE_CLICKED) {
+        Some(Key::DoubleClick { line, col })
+    } else if pressed(nc::BUTTON1_CLICKED) {
+        Some(Key::Click { line, col })
+    } else if pressed(nc::BUTTON4_PRESSED) {
+        Some(Key::WheelUp)
+    } else if pressed(nc::BUTTON5_PRESSED) {
+        Some(Key::WheelDown)
+    } else {
+        None
+    }
+}
+
+/* Reads the rest of a CSI (`ESC [`) or SS3 (`ESC O`) sequence that
+ * ncurses doesn't know, e.g. a function key with modifiers, so that it
+ * isn't taken for typed text. CSI sequences end with a character from
+ * `@` to `~`, SS3 ones right after the introducer. */
+fn read_sequence(introducer: char) -> String {
+    let mut sequence = introducer.to_string();
+    while let Some(nc::WchResult::Char(ch)) = nc::get_wch() {
+        sequence.extend(std::char::from_u32(ch));
+        if introducer == 'O' || (0x40..=0x7e).contains(&ch) {
+            break;
+        }
+    }
+    sequence
+}
+
+/* Without bracketed paste, a pasted newline would run whatever is
+ * selected. ncurses has no call for it, so the sequence goes out as is. */
+fn set_bracketed_paste(on: bool) {
+    let mut out = stdout();
+    let _ = write!(out, "\x1b[?2004{}", if on { 'h' } else { 'l' });
+    let _ = out.flush();
+}
+
+pub fn restore() {
+    set_bracketed_paste(false);
+    nc::endwin();
+}
+
+fn attributes(style: Style) -> nc::attr_t {
+    match style {
+        Style::Normal => nc::A_NORMAL(),
+        Style::Bold => nc::A_BOLD(),
+        Style::Selected => nc::COLOR_PAIR(2),
+        Style::Status => nc::COLOR_PAIR(3),
+        Style::Favorite => nc::COLOR_PAIR(4),
+        Style::Highlight => nc::COLOR_PAIR(5) | nc::A_BOLD(),
+        Style::Alert => nc::COLOR_PAIR(6),
+    }
+}
@@ -1,57 +1,81 @@
-use crate::app::{Application, View};
-use crate::util::get_shell_prompt;
-
-#[cfg(test)]
-use fake_ncurses as nc;
-#[cfg(not(test))]
-use ncurses as nc;
+use crate::app::{Application, Hint, View};
+use crate::secrets::Scanner;
+use crate::shell::get_shell_prompt;
+use hstr::Error;
 
+use crate::session::{CTRL_E, CTRL_G, CTRL_P, CTRL_SLASH, CTRL_T};
+use crate::terminal::{Key, Style, Terminal};
 use regex::Regex;
 
+/// Columns before each command: its row number, then `!` if it holds
+/// a secret.
+const GUTTER: i32 = 2;
+
+/// Rows kept on screen above and below the selection when scrolling.
+const SCROLL_MARGIN: i32 = 3;
+
 const LABEL: &str =
     "Type to filter, UP/DOWN move, RET/TAB select, DEL remove, ESC quit, C-f add/rm fav";
 
 pub struct UserInterface {
     pub page: i32,
+    /// In scrolling mode, the index of the first command on screen,
+    /// which is then used instead of the page.
+    pub top: i32,
+    /// The row of the selection on screen.
     pub selected: i32,
+    pub scrolling: bool,
+    /// Whether the prompt is at the bottom, with the list above it
+    /// starting from the bottom.
+    pub bottom_up: bool,
+    pub searching: bool,
+    pub presenting: bool,
+    pub masks: Scanner,
+    pub error: Option<String>,
+    pub prompt: String,
+    pub term: Box<dyn Terminal>,
 }
 
 impl UserInterface {
-    pub fn new() -> Self {
+    pub fn new(term: Box<dyn Terminal>) -> Self {
         Self {
             page: 1,
+            top: 0,
             selected: 0,
+            scrolling: false,
+            bottom_up: false,
+            searching: false,
+            presenting: false,
+            masks: Scanner::default(),
+            error: None,
+            prompt: get_shell_prompt(),
+            term,
         }
     }
 
-    pub fn init_color_pairs(&self) {
-        nc::start_color();
-        nc::init_pair(1, nc::COLOR_WHITE, nc::COLOR_BLACK); // normal
-        nc::init_pair(2, nc::COLOR_WHITE, nc::COLOR_GREEN); // highlighted-green (selected item)
-        nc::init_pair(3, nc::COLOR_BLACK, nc::COLOR_WHITE); // highlighted-white (status)
-        nc::init_pair(4, nc::COLOR_CYAN, nc::COLOR_BLACK); // white (favorites)
-        nc::init_pair(5, nc::COLOR_RED, nc::COLOR_BLACK); // red (searched items)
-        nc::init_pair(6, nc::COLOR_WHITE, nc::COLOR_RED); // higlighted-red
-    }
-
-    pub fn populate_screen(&self, app: &Application) {
+    pub fn populate_screen(&mut self, app: &Application) {
         let commands = self.get_page(app.get_commands());
+        let width = self.term.cols() as usize - 1;
         for (index, entry) in commands.iter().enumerate() {
-            nc::mvaddstr(
-                index as i32 + 3,
-                1,
-                &format!("{1:0$}", nc::COLS() as usize - 1, entry),
-            );
-            let substring_indexes = self.get_substring_indexes(&entry, &app.search_string);
-            if !substring_indexes.is_empty() {
-                for (idx, letter) in entry.chars().enumerate() {
-                    if substring_indexes.contains(&idx) {
-                        nc::attron(nc::COLOR_PAIR(5) | nc::A_BOLD());
-                        nc::mvaddch(index as i32 + 3, idx as i32 + 1, letter as nc::chtype);
-                        nc::attroff(nc::COLOR_PAIR(5) | nc::A_BOLD());
-                    } else {
-                        nc::mvaddch(index as i32 + 3, idx as i32 + 1, letter as nc::chtype);
-                    }
+            let row = index as i32 + 3;
+            let shown = self.display(entry);
+            self.print(row, 0, &row_number(index), Style::Normal);
+            if app.secrets.contains_secret(entry) {
+                self.print(row, 1, "!", Style::Highlight);
+            } else {
+                self.print(row, 1, " ", Style::Normal);
+            }
+            let line = format!("{1:0$}", width + 1 - GUTTER as usize, shown);
+            self.print(row, GUTTER, &line, Style::Normal);
+            let substring_indexes = self.get_substring_indexes(&shown, &app.search_string);
+            for (idx, letter) in shown.chars().enumerate() {
+                if substring_indexes.contains(&idx) {
+                    self.print(
+                        row,
+                        idx as i32 + GUTTER,
+                        &letter.to_string(),
+                        Style::Highlight,
+                    );
                 }
             }
             if app
@@ -60,53 +84,168 @@ impl UserInterface {
                 .unwrap()
                 .get(&View::Favorites)
                 .unwrap()
-                .contains(&entry)
+                .contains(entry)
             {
-                nc::attron(nc::COLOR_PAIR(4));
-                nc::mvaddstr(
-                    index as i32 + 3,
-                    1,
-                    &format!("{1:0$}", nc::COLS() as usize - 1, entry),
-                );
-                nc::attroff(nc::COLOR_PAIR(4));
+                self.print(row, GUTTER, &line, Style::Favorite);
             }
             if index == self.selected as usize {
-                nc::attron(nc::COLOR_PAIR(2));
-                nc::mvaddstr(
-                    index as i32 + 3,
-                    1,
-                    &format!("{1:0$}", nc::COLS() as usize - 1, entry),
-                );
-                nc::attroff(nc::COLOR_PAIR(2));
+                self.print(row, GUTTER, &line, Style::Selected);
             }
         }
-        nc::mvaddstr(1, 1, LABEL);
-        nc::attron(nc::COLOR_PAIR(3));
-        nc::mvaddstr(
-            2,
-            1,
-            &format!(
-                "{1:0$}",
-                nc::COLS() as usize - 1,
-                format!(
-                    "- view:{} (C-/) - regex:{} (C-e) - case:{} (C-t) - page {}/{} -",
-                    self.display_view(app.view),
-                    self.display_regex_mode(app.regex_mode),
-                    self.display_case(app.case_sensitivity),
-                    self.page,
-                    self.total_pages(app.get_commands())
-                )
+        if commands.is_empty() && !self.searching {
+            self.populate_empty_state(app);
+        }
+        self.print(1, 1, LABEL, Style::Normal);
+        let status = self.status_line(app);
+        let style = match self.error {
+            Some(_) => Style::Alert,
+            None => Style::Status,
+        };
+        self.print(2, 1, &format!("{1:0$}", width, status), style);
+        let prompt = format!("{} {}", self.prompt, app.search_string);
+        self.print(0, 1, &prompt, Style::Normal);
+        self.term.refresh();
+    }
+
+    /// Maps the rows of the layout, where the prompt is row 0, to lines
+    /// of the screen and back. They are the same unless bottom-up.
+    pub fn screen_row(&self, y: i32) -> i32 {
+        match self.bottom_up {
+            true => self.term.lines() - 1 - y,
+            false => y,
+        }
+    }
+
+    fn print(&mut self, y: i32, x: i32, text: &str, style: Style) {
+        let y = self.screen_row(y);
+        self.term.print(y, x, text, style);
+    }
+
+    /// The items of the status line, each with the key that toggles it,
+    /// which is shown next to it and pressed by clicking it.
+    fn status_items(&self, app: &Application) -> Vec<(String, Option<Key>)> {
+        if let Some(error) = &self.error {
+            return vec![(error.clone(), None)];
+        }
+        let mut items = vec![
+            (
+                format!("view:{}", self.display_view(app.view)),
+                Some(Key::Char(CTRL_SLASH)),
             ),
-        );
-        nc::attroff(nc::COLOR_PAIR(3));
-        nc::mvaddstr(
-            0,
-            1,
-            &format!("{} {}", get_shell_prompt(), app.search_string),
-        );
+            (
+                format!("regex:{}", self.display_regex_mode(app.regex_mode)),
+                Some(Key::Char(CTRL_E)),
+            ),
+            (
+                format!("case:{}", self.display_case(app.case_sensitivity)),
+                Some(Key::Char(CTRL_T)),
+            ),
+            (self.display_position(app.get_commands()), None),
+        ];
+        if app.show_blacklisted {
+            items.push((String::from("blacklisted shown"), Some(Key::Char(CTRL_G))));
+        }
+        if self.presenting {
+            items.push((String::from("presenting"), Some(Key::Char(CTRL_P))));
+        }
+        if self.searching {
+            items.push((String::from("searching…"), None));
+        }
+        items
+            .into_iter()
+            .map(|(text, key)| match key {
+                /* Keys are shown as in recordings, without the `<>`. */
+                Some(key) => {
+                    let name = key.to_string();
+                    (
+                        format!("{} ({})", text, &name[1..name.len() - 1]),
+                        Some(key),
+                    )
+                }
+                None => (text, None),
+            })
+            .collect()
+    }
+
+    fn status_line(&self, app: &Application) -> String {
+        let items = self.status_items(app);
+        let texts = items.iter().map(|(text, _)| text.as_str());
+        format!("- {} -", texts.collect::<Vec<_>>().join(" - "))
+    }
+
+    /// The key of the item of the status line at the given column, if
+    /// any, e.g. C-e for the regex mode.
+    pub fn status_key_at(&self, app: &Application, col: i32) -> Option<Key> {
+        /* The status line is printed from column 1, and starts with `- `. */
+        let mut start = 3;
+        for (text, key) in self.status_items(app) {
+            let end = start + text.chars().count() as i32;
+            if (start..end).contains(&col) {
+                return key;
+            }
+            start = end + " - ".len() as i32;
+        }
+        None
+    }
+
+    /// Selects the entry on the given row of the page, if there is one.
+    pub fn select_row(&mut self, commands: &[String], row: i32) -> bool {
+        let selectable = (0..self.get_page_size(commands)).contains(&row);
+        if selectable {
+            self.selected = row;
+        }
+        selectable
+    }
+
+    /* With nothing to list, say why in the middle of the screen, and
+     * if the query only matches nothing because of the current settings,
+     * which ones to change. */
+    fn populate_empty_state(&mut self, app: &Application) {
+        let middle = (self.term.lines() - 3) / 2 + 3;
+        let cols = self.term.cols();
+        let message = self.empty_message(app);
+        self.print(middle - 1, centered(&message, cols), &message, Style::Bold);
+        let hints = self.display_hints(&app.hints());
+        self.print(middle + 1, centered(&hints, cols), &hints, Style::Normal);
     }
 
+    fn empty_message(&self, app: &Application) -> String {
+        if !app.search_string.is_empty() {
+            return String::from("no matches");
+        }
+        match app.view {
+            View::Favorites => String::from("no favorites, add one with C-f"),
+            View::Hidden => String::from("nothing hidden, hide a command with C-k"),
+            _ => String::from("history is empty"),
+        }
+    }
+
+    fn display_hints(&self, hints: &[Hint]) -> String {
+        if hints.is_empty() {
+            return String::new();
+        }
+        let hints = hints
+            .iter()
+            .map(|hint| match hint {
+                Hint::ToggleCase => String::from("toggle case (C-t)"),
+                Hint::ToggleRegex => String::from("toggle regex (C-e)"),
+                Hint::ShowBlacklisted => String::from("show blacklisted (C-g)"),
+                Hint::SwitchView(view) => {
+                    format!("switch view to {} (C-/)", self.display_view(*view))
+                }
+            })
+            .collect::<Vec<_>>();
+        format!("try: {}", hints.join(", "))
+    }
+
+    /// Turns the page, or when scrolling, moves the selection a screen
+    /// further, stopping at either end.
     pub fn turn_page(&mut self, commands: &[String], direction: i32) {
+        if self.scrolling {
+            let (index, rows) = (self.index(), self.rows());
+            self.top += direction * rows;
+            return self.select(commands, index + direction * rows);
+        }
         /* Turning the page essentially works as follows:
          *
          *  We are getting the potential page by subtracting 1
@@ -135,7 +274,7 @@ impl UserInterface {
          *  which is undefined, and rem() returns None, which means that we are
          *  on page 1.
          */
-        nc::clear();
+        self.term.clear();
         let potential_page = self.page - 1 + direction;
         self.page = match i32::checked_rem_euclid(potential_page, self.total_pages(commands)) {
             Some(x) => x + 1,
@@ -144,49 +283,153 @@ impl UserInterface {
     }
 
     pub fn move_selected(&mut self, commands: &[String], direction: i32) {
-        let page_size = self.get_page_size(commands);
-        self.selected += direction;
-        if let Some(x) = i32::checked_rem_euclid(self.selected, page_size) {
-            self.selected = x;
-            if direction == 1 && self.selected == 0 {
-                self.turn_page(commands, 1);
-            } else if direction == -1 && self.selected == (page_size - 1) {
-                self.turn_page(commands, -1);
-                self.selected = self.get_page_size(commands) - 1;
+        /* Moving past either end wraps around to the other. */
+        match i32::checked_rem_euclid(self.index() + direction, commands.len() as i32) {
+            Some(index) => self.select(commands, index),
+            None => self.selected = 0,
+        }
+    }
+
+    /// Selects the command at the given percentage of the list, from
+    /// 0 for the first one to 100 for the last one.
+    pub fn jump(&mut self, commands: &[String], percent: i32) {
+        self.select(commands, commands.len() as i32 * percent / 100);
+    }
+
+    /// Selects the command at the given index, or the nearest one,
+    /// and brings it on screen.
+    pub fn select(&mut self, commands: &[String], index: i32) {
+        let rows = self.rows();
+        let index = index.min(commands.len() as i32 - 1).max(0);
+        if self.scrolling {
+            let margin = SCROLL_MARGIN.min((rows - 1) / 2);
+            let last_top = (commands.len() as i32 - rows).max(0);
+            self.top = self
+                .top
+                .min(index - margin)
+                .max(index + margin + 1 - rows)
+                .min(last_top)
+                .max(0);
+            self.selected = index - self.top;
+        } else {
+            let page = index / rows + 1;
+            if page != self.page {
+                self.term.clear();
             }
+            self.page = page;
+            self.selected = index % rows;
         }
     }
 
-    pub fn get_selected(&self, commands: &[String]) -> String {
-        String::from(
-            self.get_page(&commands)
-                .get(self.selected as usize)
-                .unwrap(),
-        )
+    /// Back to the first command, e.g. after a new search.
+    pub fn select_first(&mut self) {
+        self.page = 1;
+        self.top = 0;
+        self.selected = 0;
     }
 
-    pub fn prompt_for_deletion(&self, command: &str) {
-        nc::mvaddstr(1, 0, &format!("{1:0$}", nc::COLS() as usize, ""));
-        nc::attron(nc::COLOR_PAIR(6));
-        nc::mvaddstr(
-            1,
-            1,
-            &format!("Do you want to delete all occurences of {}? y/n", command),
+    /// Moves the selection to wherever the selected command ended up
+    /// after the commands changed, or keeps it within bounds if the
+    /// command is gone.
+    pub fn keep_selected(&mut self, old: &[String], new: &[String]) {
+        let command = self.get_selected(old);
+        match new.iter().position(|c| Some(c) == command.as_ref()) {
+            Some(index) => self.select(new, index as i32),
+            None if self.scrolling => self.select(new, self.index()),
+            None => {
+                self.page = self.page.min(self.total_pages(new)).max(1);
+                self.selected = self.selected.min(self.get_page_size(new) - 1).max(0);
+            }
+        }
+    }
+
+    pub fn get_selected(&self, commands: &[String]) -> Option<String> {
+        self.get_page(commands).get(self.selected as usize).cloned()
+    }
+
+    /// Shows the error, if any, on the status line until the next key press.
+    pub fn report<T>(&mut self, result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    pub fn prompt_for_deletion(&mut self, command: &str, view: View, lines: usize) {
+        let blank = format!("{1:0$}", self.term.cols() as usize, "");
+        self.print(1, 0, &blank, Style::Normal);
+        let prompt = self.deletion_prompt(&self.display(command), view, lines);
+        self.print(1, 1, &prompt, Style::Alert);
+        self.term.refresh();
+    }
+
+    /// Replaces the screen with the list of commands about to be deleted.
+    pub fn prompt_for_bulk_deletion(&mut self, matches: &[(String, usize)]) {
+        let lines = matches.iter().map(|(_, n)| n).sum::<usize>();
+        let shown = (self.term.lines() as usize).saturating_sub(4);
+        self.term.clear();
+        let prompt = format!(
+            "Delete these {} commands from history ({} line{})? y/n",
+            matches.len(),
+            lines,
+            if lines == 1 { "" } else { "s" }
         );
-        nc::attroff(nc::COLOR_PAIR(6));
+        self.print(1, 1, &prompt, Style::Alert);
+        for (index, (command, n)) in matches.iter().take(shown).enumerate() {
+            let line = format!("{:>5}x {}", n, self.display(command));
+            self.print(index as i32 + 3, 1, &line, Style::Normal);
+        }
+        if matches.len() > shown {
+            let more = format!("... and {} more", matches.len() - shown);
+            self.print(self.term.lines() - 1, 1, &more, Style::Normal);
+        }
+        self.term.refresh();
+    }
+
+    fn deletion_prompt(&self, command: &str, view: View, lines: usize) -> String {
+        match view {
+            View::Favorites => format!(
+                "Remove {} from favorites? History is left as is. y/n",
+                command
+            ),
+            _ => format!(
+                "Delete all occurrences of {} from history ({} line{})? y/n",
+                command,
+                lines,
+                if lines == 1 { "" } else { "s" }
+            ),
+        }
     }
 
     fn total_pages(&self, commands: &[String]) -> i32 {
-        commands.chunks(nc::LINES() as usize - 3).len() as i32
+        commands.chunks(self.rows() as usize).len() as i32
     }
 
+    /// The commands on screen.
     fn get_page(&self, commands: &[String]) -> Vec<String> {
-        match commands
-            .chunks(nc::LINES() as usize - 3)
-            .nth(self.page as usize - 1)
-        {
-            Some(cmds) => cmds.to_vec(),
-            None => Vec::new(),
+        let rows = self.rows() as usize;
+        let first = match self.scrolling {
+            true => self.top as usize,
+            false => (self.page as usize - 1) * rows,
+        };
+        commands.iter().skip(first).take(rows).cloned().collect()
+    }
+
+    /// How many commands fit under the prompt, the label and the status
+    /// line, and at least one, so that the page math holds however short
+    /// the screen.
+    fn rows(&self) -> i32 {
+        (self.term.lines() - 3).max(1)
+    }
+
+    /// The index of the selected command in the whole list.
+    fn index(&self) -> i32 {
+        match self.scrolling {
+            true => self.top + self.selected,
+            false => (self.page - 1) * self.rows() + self.selected,
         }
     }
 
@@ -194,6 +437,15 @@ impl UserInterface {
         self.get_page(commands).len() as i32
     }
 
+    /// The command as it is shown on screen, with anything sensitive
+    /// masked while presenting.
+    fn display(&self, command: &str) -> String {
+        match self.presenting {
+            true => self.masks.redact(command),
+            false => command.to_string(),
+        }
+    }
+
     fn get_substring_indexes<'a>(&self, string: &'a str, substring: &'a str) -> Vec<usize> {
         match Regex::new(substring) {
             Ok(r) => r.find_iter(string).flat_map(|m| m.range()).collect(),
@@ -206,6 +458,7 @@ impl UserInterface {
             View::Sorted => String::from("sorted"),
             View::Favorites => String::from("favorites"),
             View::All => String::from("all"),
+            View::Hidden => String::from("hidden"),
         }
     }
 
@@ -222,13 +475,233 @@ impl UserInterface {
             false => String::from("off"),
         }
     }
+
+    fn display_position(&self, commands: &[String]) -> String {
+        match commands.len() {
+            0 => String::from("0/0"),
+            total => format!("{}/{}", self.index() + 1, total),
+        }
+    }
+}
+
+/// The number the row is picked with, with Alt, for the first nine rows.
+fn row_number(index: usize) -> String {
+    match index {
+        0..=8 => (index + 1).to_string(),
+        _ => String::from(" "),
+    }
+}
+
+fn centered(text: &str, cols: i32) -> i32 {
+    ((cols - text.chars().count() as i32) / 2).max(0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::app::fixtures::*;
+    use crate::app::{app_with_fake_history, detached_app};
+    use crate::terminal::fake::Fake;
+    use crate::terminal::fake::Screen;
     use rstest::rstest;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/snapshots");
+
+    fn fake_user_interface(lines: i32, cols: i32) -> (UserInterface, Rc<RefCell<Screen>>) {
+        let fake = Fake::new(lines, cols);
+        let screen = fake.screen();
+        let mut user_interface = UserInterface::new(Box::new(fake));
+        user_interface.prompt = String::from("user@host$");
+        (user_interface, screen)
+    }
+
+    #[rstest(
+        name,
+        search_string,
+        case_sensitivity,
+        favorite,
+        selected,
+        error,
+        case("sorted", "", false, None, 0, None),
+        case("search", "spam", false, Some("grep -r spam ."), 1, None),
+        case("no_matches", "CARD", true, None, 0, None),
+        case(
+            "error",
+            "",
+            false,
+            None,
+            2,
+            Some("could not write /home/user/.bash_history: permission denied")
+        )
+    )]
+    fn populate_screen(
+        name: &str,
+        search_string: &str,
+        case_sensitivity: bool,
+        favorite: Option<&str>,
+        selected: i32,
+        error: Option<&str>,
+        mut app_with_fake_history: Application,
+    ) {
+        let (mut user_interface, screen) = fake_user_interface(10, 60);
+        app_with_fake_history.to_restore = app_with_fake_history.commands.clone();
+        app_with_fake_history.search_string = search_string.to_string();
+        app_with_fake_history.case_sensitivity = case_sensitivity;
+        app_with_fake_history.search();
+        if let Some(command) = favorite {
+            app_with_fake_history.add_or_rm_fav(command.to_string());
+        }
+        user_interface.selected = selected;
+        user_interface.error = error.map(String::from);
+        user_interface.populate_screen(&app_with_fake_history);
+        screen.borrow().assert_snapshot(SNAPSHOTS, name);
+    }
+
+    #[rstest(
+        lines,
+        expected,
+        case(6, vec!["2x ls -la", "1x cd", "... and 1 more"]),
+        case(3, vec!["... and 3 more"])
+    )]
+    fn prompt_for_bulk_deletion(lines: i32, expected: Vec<&str>) {
+        let (mut user_interface, screen) = fake_user_interface(lines, 60);
+        let matches = vec![
+            (String::from("ls -la"), 2),
+            (String::from("cd"), 1),
+            (String::from("make"), 1),
+        ];
+        user_interface.prompt_for_bulk_deletion(&matches);
+        let screen = screen.borrow();
+        assert!(screen.line(1).contains("Delete these 3 commands"));
+        let shown = (2..lines)
+            .map(|y| screen.line(y).trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(shown, expected);
+    }
+
+    #[rstest(
+        lines,
+        scrolling,
+        case(1, false),
+        case(2, false),
+        case(3, true),
+        case(4, true)
+    )]
+    fn tiny_screen(lines: i32, scrolling: bool, app_with_fake_history: Application) {
+        let (mut user_interface, _) = fake_user_interface(lines, 40);
+        user_interface.scrolling = scrolling;
+        let commands = app_with_fake_history.get_commands().to_vec();
+        user_interface.populate_screen(&app_with_fake_history);
+        user_interface.move_selected(&commands, 1);
+        assert_eq!(user_interface.index(), 1);
+        user_interface.turn_page(&commands, 1);
+        assert_eq!(user_interface.index(), 2);
+        user_interface.jump(&commands, 100);
+        assert_eq!(user_interface.index(), commands.len() as i32 - 1);
+        assert_eq!(user_interface.get_page(&commands).len(), 1);
+        user_interface.populate_screen(&app_with_fake_history);
+    }
+
+    #[rstest()]
+    fn populate_screen_bottom_up(mut app_with_fake_history: Application) {
+        let (mut user_interface, screen) = fake_user_interface(10, 60);
+        app_with_fake_history.search_string = String::from("git");
+        app_with_fake_history.search();
+        user_interface.bottom_up = true;
+        user_interface.selected = 1;
+        user_interface.populate_screen(&app_with_fake_history);
+        screen.borrow().assert_snapshot(SNAPSHOTS, "bottom_up");
+    }
+
+    #[rstest(
+        show_blacklisted,
+        presenting,
+        searching,
+        expected,
+        case(false, false, false, " - case:insensitive (C-t) - 1/23 -"),
+        case(true, false, false, " - 1/23 - blacklisted shown (C-g) -"),
+        case(false, true, true, " - 1/23 - presenting (C-p) - searching… -")
+    )]
+    fn status_line(
+        show_blacklisted: bool,
+        presenting: bool,
+        searching: bool,
+        expected: &str,
+        mut app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        app_with_fake_history.show_blacklisted = show_blacklisted;
+        user_interface.presenting = presenting;
+        user_interface.searching = searching;
+        let status = user_interface.status_line(&app_with_fake_history);
+        assert!(status.ends_with(expected), "{}", status);
+    }
+
+    #[rstest(
+        col,
+        error,
+        expected,
+        case(0, None, None),
+        case(3, None, Some(Key::Char('\u{1f}'))),
+        case(19, None, Some(Key::Char('\u{1f}'))),
+        case(20, None, None),
+        case(23, None, Some(Key::Char('\u{5}'))),
+        case(50, None, Some(Key::Char('\u{14}'))),
+        case(70, None, None),
+        case(90, None, None),
+        case(3, Some("could not write (C-e)"), None)
+    )]
+    fn status_key_at(
+        col: i32,
+        error: Option<&str>,
+        expected: Option<Key>,
+        app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        user_interface.error = error.map(String::from);
+        assert_eq!(
+            user_interface.status_key_at(&app_with_fake_history, col),
+            expected
+        );
+    }
+
+    #[rstest(
+        row,
+        expected,
+        case(-1, false),
+        case(0, true),
+        case(6, true),
+        case(7, false)
+    )]
+    fn select_row(row: i32, expected: bool, app_with_fake_history: Application) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        user_interface.selected = 3;
+        let commands = app_with_fake_history.get_commands();
+        assert_eq!(user_interface.select_row(commands, row), expected);
+        assert_eq!(user_interface.selected, if expected { row } else { 3 });
+    }
+
+    #[rstest()]
+    fn populate_screen_styles(mut app_with_fake_history: Application) {
+        let (mut user_interface, screen) = fake_user_interface(10, 40);
+        app_with_fake_history.search_string = String::from("spam");
+        app_with_fake_history.search();
+        app_with_fake_history.add_or_rm_fav(String::from("grep -r spam ."));
+        user_interface.selected = 1;
+        user_interface.populate_screen(&app_with_fake_history);
+        let screen = screen.borrow();
+        assert_eq!(screen.line(0), " user@host$ spam");
+        assert_eq!(screen.attrs(2), format!(" {}", "t".repeat(39)));
+        assert_eq!(screen.line(3), "1 cat spam");
+        assert_eq!(screen.attrs(3), "      hhhh");
+        assert_eq!(screen.line(4), "2 cat SPAM");
+        assert_eq!(screen.attrs(4), format!("  {}", "s".repeat(38)));
+        assert_eq!(screen.line(5), "3 grep -r spam .");
+        assert_eq!(screen.attrs(5), format!("  {}", "f".repeat(38)));
+        assert_eq!(screen.line(6), "");
+    }
 
     #[rstest(
         page,
@@ -267,13 +740,56 @@ mod tests {
         case(5, vec![])
     )]
     fn get_page(page: i32, expected: Vec<&str>, app_with_fake_history: Application) {
-        let mut user_interface = UserInterface::new();
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
         let commands = app_with_fake_history.get_commands();
         user_interface.page = page;
-        assert_eq!(
-            user_interface.get_page(commands),
-            expected
-        );
+        assert_eq!(user_interface.get_page(commands), expected);
+    }
+
+    #[rstest(
+        direction,
+        empty,
+        expected,
+        case(1, false, (1, 1)),
+        case(-1, false, (4, 1)),
+        case(1, true, (1, 0)),
+        case(-1, true, (1, 0))
+    )]
+    fn move_selected(
+        direction: i32,
+        empty: bool,
+        expected: (i32, i32),
+        app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        let commands = match empty {
+            true => &[],
+            false => app_with_fake_history.get_commands(),
+        };
+        user_interface.move_selected(commands, direction);
+        assert_eq!((user_interface.page, user_interface.selected), expected);
+    }
+
+    #[rstest(
+        page,
+        selected,
+        expected,
+        case(1, 2, Some("git add .")),
+        case(4, 1, Some("gpg --card-status")),
+        case(4, 2, None),
+        case(5, 0, None)
+    )]
+    fn get_selected(
+        page: i32,
+        selected: i32,
+        expected: Option<&str>,
+        app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        let commands = app_with_fake_history.get_commands();
+        user_interface.page = page;
+        user_interface.selected = selected;
+        assert_eq!(user_interface.get_selected(commands).as_deref(), expected);
     }
 
     #[rstest(
@@ -290,13 +806,43 @@ mod tests {
         case(1, 4, -1),
     )]
     fn turn_page(current: i32, expected: i32, direction: i32, app_with_fake_history: Application) {
-        let mut user_interface = UserInterface::new();
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
         let commands = app_with_fake_history.get_commands();
         user_interface.page = current;
         user_interface.turn_page(commands, direction);
         assert_eq!(user_interface.page, expected)
     }
 
+    #[rstest(
+        scrolling,
+        index,
+        expected,
+        case(false, 10, (2, 0, 3)),
+        case(false, 99, (4, 0, 1)),
+        case(true, 2, (1, 0, 2)),
+        case(true, 10, (1, 7, 3)),
+        case(true, 22, (1, 16, 6)),
+        case(true, -5, (1, 0, 0))
+    )]
+    fn select(
+        scrolling: bool,
+        index: i32,
+        expected: (i32, i32, i32),
+        app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        user_interface.scrolling = scrolling;
+        user_interface.select(app_with_fake_history.get_commands(), index);
+        assert_eq!(
+            (
+                user_interface.page,
+                user_interface.top,
+                user_interface.selected
+            ),
+            expected
+        );
+    }
+
     #[rstest(
         string,
         substring,
@@ -306,7 +852,7 @@ mod tests {
         case("ping -c 10 www.google.com", "[0-9]+", vec![8, 9])
     )]
     fn get_substring_indexes(string: &str, substring: &str, expected: Vec<usize>) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         assert_eq!(
             user_interface.get_substring_indexes(string, substring),
             expected
@@ -315,42 +861,163 @@ mod tests {
 
     #[rstest()]
     fn get_page_size(app_with_fake_history: Application) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         let commands = app_with_fake_history.get_commands();
         assert_eq!(user_interface.get_page_size(commands), 7);
     }
 
     #[rstest()]
     fn total_pages(app_with_fake_history: Application) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         let commands = app_with_fake_history.get_commands();
         assert_eq!(user_interface.total_pages(commands), 4);
     }
 
+    #[rstest(
+        view,
+        lines,
+        expected,
+        case(
+            View::Favorites,
+            0,
+            "Remove ls from favorites? History is left as is. y/n"
+        ),
+        case(
+            View::Sorted,
+            1,
+            "Delete all occurrences of ls from history (1 line)? y/n"
+        ),
+        case(
+            View::All,
+            12,
+            "Delete all occurrences of ls from history (12 lines)? y/n"
+        )
+    )]
+    fn deletion_prompt(view: View, lines: usize, expected: &str) {
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
+        assert_eq!(user_interface.deletion_prompt("ls", view, lines), expected);
+    }
+
     #[rstest(
         value,
         expected,
         case(View::Sorted, "sorted"),
         case(View::Favorites, "favorites"),
-        case(View::All, "all")
+        case(View::All, "all"),
+        case(View::Hidden, "hidden")
     )]
     fn display_view(value: View, expected: &str) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         assert_eq!(user_interface.display_view(value), expected.to_string());
     }
 
+    #[rstest(
+        view,
+        search_string,
+        expected,
+        case(View::Sorted, "", "history is empty"),
+        case(View::Favorites, "", "no favorites, add one with C-f"),
+        case(View::Hidden, "", "nothing hidden, hide a command with C-k"),
+        case(View::All, "zzz", "no matches")
+    )]
+    fn empty_message(view: View, search_string: &str, expected: &str) {
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
+        let mut app = detached_app();
+        app.view = view;
+        app.search_string = String::from(search_string);
+        assert_eq!(user_interface.empty_message(&app), expected);
+    }
+
+    #[rstest(
+        hints,
+        expected,
+        case(vec![], ""),
+        case(vec![Hint::ToggleCase], "try: toggle case (C-t)"),
+        case(
+            vec![Hint::ToggleRegex, Hint::SwitchView(View::All)],
+            "try: toggle regex (C-e), switch view to all (C-/)"
+        )
+    )]
+    fn display_hints(hints: Vec<Hint>, expected: &str) {
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
+        assert_eq!(user_interface.display_hints(&hints), expected);
+    }
+
     #[rstest(value, expected, case(true, "sensitive"), case(false, "insensitive"))]
     fn display_case(value: bool, expected: &str) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         assert_eq!(user_interface.display_case(value), expected.to_string());
     }
 
     #[rstest(value, expected, case(true, "on"), case(false, "off"))]
     fn display_regex_mode(value: bool, expected: &str) {
-        let user_interface = UserInterface::new();
+        let user_interface = UserInterface::new(Box::new(Fake::default()));
         assert_eq!(
             user_interface.display_regex_mode(value),
             expected.to_string()
         );
     }
+    #[rstest(
+        presenting,
+        expected,
+        case(true, "mysql -u root -p**** -h db.example.com"),
+        case(false, "mysql -u root -phunter2 -h db.example.com")
+    )]
+    fn display(presenting: bool, expected: &str) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        user_interface.presenting = presenting;
+        assert_eq!(
+            user_interface.display("mysql -u root -phunter2 -h db.example.com"),
+            expected
+        );
+    }
+
+    #[rstest(
+        bottom_up,
+        y,
+        expected,
+        case(false, 0, 0),
+        case(false, 9, 9),
+        case(true, 0, 9),
+        case(true, 3, 6)
+    )]
+    fn screen_row(bottom_up: bool, y: i32, expected: i32) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        user_interface.bottom_up = bottom_up;
+        assert_eq!(user_interface.screen_row(y), expected);
+    }
+
+    #[rstest(index, expected, case(0, "1"), case(8, "9"), case(9, " "))]
+    fn row_number(index: usize, expected: &str) {
+        assert_eq!(super::row_number(index), expected);
+    }
+
+    #[rstest(
+        page,
+        selected,
+        new,
+        expected_page,
+        expected_selected,
+        case(2, 3, vec!["lsusb"], 1, 0),
+        case(2, 3, vec!["ls -la", "cargo build", "lsusb"], 1, 2),
+        case(3, 5, vec!["ls -la", "cargo build"], 1, 1),
+    )]
+    fn keep_selected(
+        page: i32,
+        selected: i32,
+        new: Vec<&str>,
+        expected_page: i32,
+        expected_selected: i32,
+        app_with_fake_history: Application,
+    ) {
+        let mut user_interface = UserInterface::new(Box::new(Fake::default()));
+        let new = new.iter().map(|&x| x.into()).collect::<Vec<String>>();
+        user_interface.page = page;
+        user_interface.selected = selected;
+        user_interface.keep_selected(app_with_fake_history.get_commands(), &new);
+        assert_eq!(
+            (user_interface.page, user_interface.selected),
+            (expected_page, expected_selected)
+        );
+    }
 }
@@ -1,40 +1,33 @@
-use libc::{ioctl, TIOCSTI};
-use std::env;
+use crate::error::Error;
 use std::fs::{create_dir_all, write, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
-pub fn read_file(path: String) -> Result<Vec<String>, std::io::Error> {
-    let p = dirs::home_dir().unwrap().join(PathBuf::from(path));
-    if !Path::new(p.as_path()).exists() {
+pub fn home_dir() -> Result<PathBuf, Error> {
+    dirs::home_dir().ok_or(Error::NoHomeDir)
+}
+
+pub fn read_file(p: &Path) -> Result<Vec<String>, Error> {
+    read_or_create(p).map_err(|e| Error::Read(p.to_path_buf(), e))
+}
+
+fn read_or_create(p: &Path) -> Result<Vec<String>, std::io::Error> {
+    if !p.exists() {
         create_dir_all(p.parent().unwrap())?;
-        File::create(p.as_path())?;
+        File::create(p)?;
         Ok(Vec::new())
     } else {
-        let file = File::open(p).unwrap();
-        let reader = BufReader::new(file);
+        let reader = BufReader::new(File::open(p)?);
         reader.lines().collect::<Result<Vec<_>, _>>()
     }
 }
 
-pub fn write_file(path: String, thing: &[String]) -> Result<(), std::io::Error> {
-    let p = dirs::home_dir().unwrap().join(PathBuf::from(path));
-    write(p, thing.join("\n"))?;
-    Ok(())
-}
-
-pub fn echo(command: String) {
-    unsafe {
-        for byte in command.as_bytes() {
-            ioctl(0, TIOCSTI, byte);
-        }
+pub fn write_file(p: &Path, thing: &[String]) -> Result<(), Error> {
+    /* Shells append to the history without checking whether
+     * the last line is terminated, so it always has to be. */
+    let mut contents = thing.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
     }
-}
-
-pub fn get_shell_prompt() -> String {
-    format!(
-        "{}@{}$",
-        env::var("USER").unwrap(),
-        gethostname::gethostname().into_string().unwrap()
-    )
+    write(p, contents).map_err(|e| Error::Write(p.to_path_buf(), e))
 }
@@ -0,0 +1,78 @@
+use hstr::{Error, Files, List};
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use std::ffi::OsString;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Change {
+    History,
+    Favorites,
+}
+
+/* Watches the history and favorites files for writes made by other
+ * processes, e.g. `history -a` running from PROMPT_COMMAND in another
+ * terminal, or another instance of hstr-rs toggling a favorite.
+ *
+ * The parent directories are watched rather than the files themselves,
+ * so that files which get replaced by renaming are followed, too.
+ */
+pub struct Watcher {
+    inotify: Inotify,
+    files: Vec<(WatchDescriptor, OsString, Change)>,
+    buffer: [u8; 4096],
+}
+
+impl Watcher {
+    pub fn new(files: &Files) -> Result<Self, Error> {
+        let mut watcher = Self {
+            inotify: Inotify::init()?,
+            files: Vec::new(),
+            buffer: [0; 4096],
+        };
+        watcher.watch(&files.history(), Change::History)?;
+        watcher.watch(&List::Favorites.path(files), Change::Favorites)?;
+        Ok(watcher)
+    }
+
+    fn watch(&mut self, path: &Path, change: Change) -> Result<(), std::io::Error> {
+        let wd = self.inotify.add_watch(
+            path.parent().unwrap(),
+            WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO,
+        )?;
+        self.files
+            .push((wd, path.file_name().unwrap().to_os_string(), change));
+        Ok(())
+    }
+
+    /// Returns the files that were written to since the last call,
+    /// without blocking.
+    pub fn changes(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        loop {
+            /* Besides WouldBlock, which means there are no more events,
+             * errors are not worth interrupting the user for. */
+            let events = match self.inotify.read_events(&mut self.buffer) {
+                Ok(events) => events,
+                Err(_) => break,
+            };
+            let mut empty = true;
+            for event in events {
+                empty = false;
+                let change = self
+                    .files
+                    .iter()
+                    .find(|(wd, name, _)| *wd == event.wd && Some(name.as_os_str()) == event.name)
+                    .map(|(_, _, change)| *change);
+                if let Some(change) = change {
+                    if !changes.contains(&change) {
+                        changes.push(change);
+                    }
+                }
+            }
+            if empty {
+                break;
+            }
+        }
+        changes
+    }
+}